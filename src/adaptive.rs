@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::path::Path;
+use rand::Rng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use serde::{Serialize, Serializer};
+use crate::err::Error;
+use crate::i18n::tf;
+use crate::problem::{Fact, Problem};
+use crate::progress::{fact_families, load_records, summarize, FamilyStats, Record};
+
+// 少于这个次数的题型不调整权重
+const MIN_ATTEMPTS: usize = 3;
+// 全部答对且不慢于平均用时的题型的权重
+const MASTERED_WEIGHT: f64 = 0.5;
+// 错误率对权重的影响，错误率 100% 时权重增加这么多
+const ERROR_WEIGHT: f64 = 4.0;
+// 用时对权重的影响范围，用时与平均用时之比限制在此范围内
+const SPEED_FACTOR_RANGE: (f64, f64) = (0.5, 2.0);
+
+// 按学生的作答结果计算每个题型的权重，薄弱的题型出现得更多，已掌握的更少
+#[derive(Debug, Clone)]
+pub struct FactWeights {
+    // 结果文件
+    source: String,
+    families: Vec<FamilyWeight>,
+    // 所有作答的平均用时（秒）
+    overall_secs: f64,
+}
+
+#[derive(Debug, Clone)]
+struct FamilyWeight {
+    stats: FamilyStats,
+    weight: f64,
+}
+
+impl FactWeights {
+    // 作为命令行参数的解析函数
+    pub fn load(path: &str) -> Result<FactWeights, Error> {
+        let weights = FactWeights::from_records(&load_records(Path::new(path))?);
+        Ok(FactWeights { source: path.to_string(), ..weights })
+    }
+
+    pub fn from_records(records: &[Record]) -> FactWeights {
+        let timed: Vec<u64> = records.iter().filter(|r| r.fact.is_some() && r.elapsed_ms > 0).map(|r| r.elapsed_ms).collect();
+        let overall_secs = if timed.is_empty() { 0.0 } else { timed.iter().sum::<u64>() as f64 / 1000.0 / timed.len() as f64 };
+        let families = summarize(records).into_iter()
+            .map(|stats| FamilyWeight { weight: family_weight(&stats, overall_secs), stats })
+            .collect();
+        FactWeights { source: String::new(), families, overall_secs }
+    }
+
+    // 一道题属于多个题型时取其中最大的权重，作答次数不足的题型不参与；都没有则为 1
+    pub fn weight(&self, fact: &Fact) -> f64 {
+        let weights: HashMap<&str, f64> = self.families.iter()
+            .filter(|f| f.stats.attempts >= MIN_ATTEMPTS)
+            .map(|f| (f.stats.family.as_str(), f.weight))
+            .collect();
+        fact_families(fact).iter()
+            .filter_map(|family| weights.get(family.as_str()).copied())
+            .reduce(f64::max)
+            .unwrap_or(1.0)
+    }
+
+    // 从候选题目中按权重抽取 count 道，候选题目本身的分布保留了原有的出题频率
+    pub fn select(&self, pool: Vec<Problem>, count: usize, rng: &mut impl Rng) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = pool
+            .choose_multiple_weighted(rng, count, |p| p.fact.map(|f| self.weight(&f)).unwrap_or(1.0))
+            .expect("weights are positive")
+            .cloned()
+            .collect();
+        problems.shuffle(rng);
+        problems
+    }
+
+    // 每个题型的权重及原因
+    pub fn explain(&self) -> Vec<String> {
+        self.families.iter().map(|f| {
+            let s = &f.stats;
+            let reason = if s.attempts < MIN_ATTEMPTS {
+                tf("adaptive.too_few", &[("attempts", &s.attempts)])
+            } else {
+                let percent = format!("{:.0}", s.accuracy() * 100.0);
+                let mut reason = tf("adaptive.accuracy", &[("correct", &s.correct), ("attempts", &s.attempts), ("percent", &percent)]);
+                if self.overall_secs > 0.0 && s.total_ms > 0 {
+                    reason += &tf("adaptive.speed", &[("avg", &format!("{:.1}", s.avg_secs())), ("overall", &format!("{:.1}", self.overall_secs))]);
+                }
+                let change = if f.weight > 1.0 { "adaptive.more" } else if f.weight < 1.0 { "adaptive.less" } else { "adaptive.unchanged" };
+                tf(change, &[("reason", &reason)])
+            };
+            tf("adaptive.family", &[("family", &format!("{:<20}", s.family)), ("weight", &format!("{:.2}", f.weight)), ("reason", &reason)])
+        }).collect()
+    }
+}
+
+// 清单中只记录结果文件
+impl Serialize for FactWeights {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+fn family_weight(stats: &FamilyStats, overall_secs: f64) -> f64 {
+    if stats.attempts < MIN_ATTEMPTS {
+        return 1.0;
+    }
+    let error_rate = 1.0 - stats.accuracy();
+    let speed_factor = if overall_secs > 0.0 && stats.total_ms > 0 {
+        (stats.avg_secs() / overall_secs).clamp(SPEED_FACTOR_RANGE.0, SPEED_FACTOR_RANGE.1)
+    } else {
+        1.0
+    };
+    (MASTERED_WEIGHT + ERROR_WEIGHT * error_rate) * speed_factor
+}
+
+#[cfg(test)]
+mod test {
+    use crate::adaptive::FactWeights;
+    use crate::problem::{Fact, Op, Problem};
+    use crate::progress::Record;
+    use crate::utils::new_rng;
+
+    fn record(left: i64, right: i64, correct: bool, elapsed_ms: u64) -> Record {
+        Record {
+            timestamp: 0,
+            question: String::new(),
+            answers: vec![],
+            given: vec![],
+            correct,
+            elapsed_ms,
+            fact: Some(Fact { left, op: Op::Add, right }),
+        }
+    }
+
+    fn weights() -> FactWeights {
+        // +9 经常出错且慢，+2 全对且快
+        let mut records = vec![];
+        for i in 0..4 {
+            records.push(record(9, 5 + i, i % 2 == 0, 4000));
+            records.push(record(2, 3 + i, true, 1000));
+        }
+        FactWeights::from_records(&records)
+    }
+
+    #[test]
+    fn test_weight() {
+        let w = weights();
+        let weak = w.weight(&Fact { left: 9, op: Op::Add, right: 6 });
+        let mastered = w.weight(&Fact { left: 2, op: Op::Add, right: 3 });
+        assert!(weak > 1.0);
+        assert!(mastered < 1.0);
+        // 没有记录的题型权重不变
+        assert_eq!(w.weight(&Fact { left: 23, op: Op::Add, right: 15 }), 1.0);
+
+        let explain = w.explain();
+        assert!(explain.iter().any(|l| l.starts_with("加9") && l.contains("答对 2/4") && l.ends_with("增加")));
+        assert!(explain.iter().any(|l| l.starts_with("加2") && l.ends_with("减少")));
+        assert!(explain.iter().any(|l| l.starts_with("加8") && l.contains("作答次数太少 (1)")));
+    }
+
+    #[test]
+    fn test_select() {
+        let w = weights();
+        let mut pool = vec![];
+        for _ in 0..50 {
+            pool.push(Problem::expr("9 + 6 =".to_string(), "15".to_string()).with_fact(9, Op::Add, 6));
+            pool.push(Problem::expr("2 + 3 =".to_string(), "5".to_string()).with_fact(2, Op::Add, 3));
+        }
+        let problems = w.select(pool, 40, &mut new_rng(Some(1)));
+        assert_eq!(problems.len(), 40);
+        let weak = problems.iter().filter(|p| p.answers[0] == "15").count();
+        assert!(weak > 25);
+    }
+}
@@ -1,60 +1,279 @@
 use std::collections::HashMap;
-use std::ops::Range;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
-use rand::{rng, Rng};
+use rand::Rng;
 use rand::distr::Uniform;
-use rand::seq::{IteratorRandom, SliceRandom};
-use crate::{AddMinusOpts, OperandConfig, OperandPattern, utils};
-use crate::utils::{add_paragraph, char_len, read_from_docx, write, write_to_docx};
+use rand::seq::{IndexedRandom, IteratorRandom, SliceRandom};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::{AddMinusOpts, Currency, OperandConfig, OperandPattern, Preset, utils};
+use crate::fraction::gcd;
+use crate::layout::{render_text, Section};
+use crate::manifest::Source;
+use crate::problem::{Fact, Op, Problem};
+use crate::utils::{new_rng, write};
+
+// 结果 -> 运算数对
+type PairMap = HashMap<u32, Vec<(u32, u32)>>;
 
 struct ParsedArgs<'a>{
     // 原始命令行参数
     origin: &'a AddMinusOpts,
     // 解析后的操作数配置
     operand_config: OperandConfig,
+    // 左/右操作数的小数位数，整数时都为0
+    decimals: (u32, u32),
 }
 
-fn parse_args(args: &AddMinusOpts) -> ParsedArgs {
-    let mut parsed_args = ParsedArgs { origin: args, operand_config: OperandConfig::Result(0) };
+fn parse_args(args: &AddMinusOpts) -> Result<ParsedArgs<'_>, Error> {
+    // -p 和 --decimals 的格式已经在命令行解析时校验过，这里只检查两者能否同时满足
+    let operand_config = parse_operand_config(&args.operand_pattern).map_err(Error::Parse)?;
+    let decimals = args.decimals.as_deref().map(parse_decimals).transpose().map_err(Error::Parse)?.unwrap_or((0, 0));
+    let parsed = ParsedArgs { origin: args, operand_config, decimals };
+    parsed.check_units()?;
+    Ok(parsed)
+}
+
+impl ParsedArgs<'_> {
+    // 数的范围、结果范围和 C* 都以最小的小数单位计，比如两位小数时 345 表示 3.45
+    fn scale(&self) -> u32 {
+        self.decimals.0.max(self.decimals.1)
+    }
+
+    // 小数位数较少的操作数只能是这个单位的倍数，比如 --decimals 2,1 时右操作数是 10 的倍数
+    fn unit(&self, places: u32) -> i64 {
+        10i64.pow(self.scale() - places)
+    }
+
+    // 左右操作数分别必须是其倍数的单位，金额是最小的常用硬币
+    fn units(&self) -> (i64, i64) {
+        match self.origin.money {
+            Some(currency) => (currency.coin(), currency.coin()),
+            None => (self.unit(self.decimals.0), self.unit(self.decimals.1)),
+        }
+    }
+
+    // 找零时可以付出的纸币，只保留能使结果落在 [-b, -e] 中的，都不能时保留全部
+    fn change_notes(&self) -> Option<Vec<i64>> {
+        let currency = self.origin.money.filter(|_| self.origin.change)?;
+        let (min, max) = (self.origin.number_min_inclusive, self.origin.number_max_inclusive);
+        let notes: Vec<i64> = currency.notes().iter().copied()
+            .filter(|&note| note.saturating_sub(max) <= self.origin.result_max_inclusive && note.saturating_sub(min) >= self.origin.result_min_inclusive)
+            .collect();
+        Some(if notes.is_empty() { currency.notes().to_vec() } else { notes })
+    }
+
+    // 模式中的常数、范围以及 -b ~ -e 中必须有单位的倍数，比如 --decimals 2,1 时 -p "*,5" 的右操作数 0.05 不是 0.1 的倍数
+    fn check_units(&self) -> Result<(), Error> {
+        let (unit_l, unit_r) = self.units();
+        let fits = match &self.operand_config {
+            OperandConfig::TwoOperand(l, r) => self.pattern_fits(l, unit_l) && self.pattern_fits(r, unit_r),
+            // 左右操作数相同，单位都是10的幂或者同一种硬币，取较大的即可
+            OperandConfig::OneOperand(p) => self.pattern_fits(p, unit_l.max(unit_r)),
+            OperandConfig::Result(_) => true,
+        };
+        if fits {
+            Ok(())
+        } else {
+            Err(Error::Parse(tf("error.pattern_unit", &[("pattern", &self.origin.operand_pattern), ("left", &unit_l), ("right", &unit_r)])))
+        }
+    }
+
+    fn pattern_fits(&self, pattern: &OperandPattern, unit: i64) -> bool {
+        let range = self.origin.number_min_inclusive..=self.origin.number_max_inclusive;
+        match pattern {
+            OperandPattern::Wildcard => has_multiple(&range, unit),
+            OperandPattern::NumberWildcard(number) => has_multiple(&range, number / gcd(*number, unit) * unit),
+            OperandPattern::Constant(c) => c % unit == 0,
+            OperandPattern::ConstantRange(r) => has_multiple(r, unit),
+        }
+    }
+}
+
+// 范围中是否有 unit 的倍数
+fn has_multiple(range: &RangeInclusive<i64>, unit: i64) -> bool {
+    range.start().div_euclid(unit).checked_add(1).and_then(|n| n.checked_mul(unit))
+        .is_some_and(|next| range.start() % unit == 0 || next <= *range.end())
+}
+
+// 小数的最多位数
+const MAX_DECIMALS: u32 = 9;
+
+// 作为 --decimals 的解析函数：N 表示左右操作数都是N位小数，L,R 分别指定
+pub fn validate_decimals(s: &str) -> Result<String, String> {
+    parse_decimals(s).map(|_| s.to_string())
+}
+
+fn parse_decimals(s: &str) -> Result<(u32, u32), String> {
+    let places: Vec<Option<u32>> = s.split(',').map(|p| p.trim().parse().ok().filter(|p| *p <= MAX_DECIMALS)).collect();
+    match places[..] {
+        [Some(n)] => Ok((n, n)),
+        [Some(l), Some(r)] => Ok((l, r)),
+        _ => Err(tf("error.decimals", &[("value", &s), ("max", &MAX_DECIMALS)])),
+    }
+}
+
+// 以 10^-scale 为单位的数，写成 places 位小数，比如 (1700, 3, 1) -> 1.7
+fn decimal_text(number: i64, scale: u32, places: u32) -> String {
+    let digits = number.unsigned_abs() / 10u64.pow(scale - places);
+    let sign = if number < 0 { "-" } else { "" };
+    if places == 0 {
+        return format!("{}{}", sign, digits);
+    }
+    let unit = 10u64.pow(places);
+    format!("{}{}.{:0p$}", sign, digits / unit, digits % unit, p = places as usize)
+}
+
+// 作为 -p 的解析函数，格式错误时给出具体原因
+pub fn validate_operand_pattern(p_str: &str) -> Result<String, String> {
+    parse_operand_config(p_str).map(|_| p_str.to_string())
+}
 
-    let p_str = &args.operand_pattern;
+fn parse_operand_config(p_str: &str) -> Result<OperandConfig, String> {
     let patterns: Vec<&str> = p_str.split(",").collect();
-    let mut operand_config;
     if patterns.len() == 2 {
         // L,R
-        operand_config = OperandConfig::TwoOperand(
-            parse_operand_pattern(patterns[0]), parse_operand_pattern(patterns[1]));
-    } else if patterns[0].starts_with("=") {
+        Ok(OperandConfig::TwoOperand(parse_operand_pattern(patterns[0])?, parse_operand_pattern(patterns[1])?))
+    } else if patterns.len() > 2 {
+        Err(tf("error.pattern_too_many", &[("pattern", &p_str)]))
+    } else if let Some(result) = patterns[0].strip_prefix("=") {
         // =A
-        operand_config = OperandConfig::Result(u16::from_str(&patterns[0][1..]).unwrap());
+        parse_number(result).map(OperandConfig::Result)
     } else {
         // L,L
-        operand_config = OperandConfig::OneOperand(parse_operand_pattern(patterns[0]))
+        Ok(OperandConfig::OneOperand(parse_operand_pattern(patterns[0])?))
     }
-    parsed_args.operand_config = operand_config;
-    parsed_args
 }
 
-fn parse_operand_pattern(pattern: &str) -> OperandPattern {
+fn parse_operand_pattern(pattern: &str) -> Result<OperandPattern, String> {
     if pattern == "*" {
-        OperandPattern::Wildcard
-    } else if pattern.ends_with("*") {
-        let p = &pattern[..pattern.len() - 1];
-        let number = u16::from_str(p).unwrap();
-        OperandPattern::NumberWildcard(number)
-    } else if pattern.contains("~") || pattern.contains("-") {
+        Ok(OperandPattern::Wildcard)
+    } else if let Some(p) = pattern.strip_suffix("*") {
+        match parse_number(p)? {
+            number if number <= 0 => Err(tf("error.pattern_multiple", &[("pattern", &pattern)])),
+            number => Ok(OperandPattern::NumberWildcard(number)),
+        }
+    } else if let Some((start, end)) = split_range(pattern) {
         // 忽略ans，指定常数范围不受min~max范围限制
-        let range: Vec<&str> = pattern.split(&['~','-']).collect();
-        OperandPattern::ConstantRange(range[0].parse::<u16>().unwrap()..u16::from_str(range[1]).unwrap() + 1)
+        if end.contains('~') {
+            return Err(tf("error.pattern_range", &[("pattern", &pattern)]));
+        }
+        let (start, end) = (parse_number(start)?, parse_number(end)?);
+        if start > end {
+            return Err(tf("error.pattern_range_order", &[("pattern", &pattern)]));
+        }
+        Ok(OperandPattern::ConstantRange(start..=end))
     } else {
         // 忽略ans，指定常数不受min~max范围限制
-        OperandPattern::Constant(u16::from_str(pattern).unwrap())
+        parse_number(pattern).map(OperandPattern::Constant)
     }
 }
 
-pub fn gen_arithmetic_to_docx_by_pattern2(args: &AddMinusOpts) {
+// 拆分范围 C~D 或 C-D，开头的 - 和括号中的 - 是负号，比如 -5~5, (-5)-(-1)
+fn split_range(pattern: &str) -> Option<(&str, &str)> {
+    if let Some(range) = pattern.split_once('~') {
+        return Some(range);
+    }
+    let mut depth = 0;
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '-' if i > 0 && depth == 0 => return Some((&pattern[..i], &pattern[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+// 负数写成 -3 或者 (-3)
+fn parse_number(s: &str) -> Result<i64, String> {
+    let number = s.trim();
+    let number = number.strip_prefix('(').and_then(|n| n.strip_suffix(')')).unwrap_or(number);
+    i64::from_str(number.trim()).map_err(|_| tf("error.pattern_number", &[("value", &s), ("min", &i64::MIN), ("max", &i64::MAX)]))
+}
+
+// 自适应出题时，先生成 count 的这么多倍的候选算式，再按权重抽取
+const ADAPTIVE_POOL_FACTOR: u16 = 4;
+
+// 随机生成一道算式最多尝试的次数，超过时认为参数之间互相矛盾，没有满足条件的算式
+pub const MAX_ATTEMPTS: u32 = 100_000;
+
+pub fn gen_problems(args: &AddMinusOpts, rng: &mut impl Rng) -> Result<Vec<Problem>, Error> {
+    match &args.adaptive {
+        Some(weights) => {
+            // 候选算式按原有的频率生成，保证仍然满足各项参数的约束
+            let pool_args = AddMinusOpts { count: args.count.saturating_mul(ADAPTIVE_POOL_FACTOR), adaptive: None, ..args.clone() };
+            Ok(weights.select(gen_problems_by_category(&pool_args, rng)?, args.count as usize, rng))
+        }
+        None => gen_problems_by_category(args, rng),
+    }
+}
+
+// 自适应出题时打印各题型的权重及原因
+pub fn print_adaptive_weights(args: &AddMinusOpts) {
+    if let Some(weights) = &args.adaptive {
+        println!("{}", t("adaptive.title"));
+        for line in weights.explain() {
+            println!("  {}", line);
+        }
+    }
+}
+
+// 按 -c 指定的类别生成全部算式
+fn gen_problems_by_category(args: &AddMinusOpts, rng: &mut impl Rng) -> Result<Vec<Problem>, Error> {
+    let c = &args.category;
+    if c.ends_with("p1") {
+        // p1: add(result [6, 16])
+        Ok(gen_problems_by_pattern1(args, rng))
+    } else if c.ends_with("p2") {
+        // p2: minus(start with 8, 10, 15~18)
+        Ok(gen_problems_by_pattern2(args, rng))
+    } else if c.ends_with("p3") {
+        // p3: minus(start with 11~14)
+        Ok(gen_problems_by_pattern3(args, rng))
+    } else if c.ends_with("p4") {
+        // p4: minus(start with 4~9)
+        Ok(gen_problems_by_pattern4(args, rng))
+    } else {
+        let parsed_args = parse_args(args)?;
+        // 指定或随机生成算式
+        (0..args.count).map(|_| gen_arithmetic_expr(&parsed_args, rng)).collect()
+    }
+}
+
+pub fn gen_arithmetic_to_docx(args: &AddMinusOpts) -> Result<(), Error> {
+    print_adaptive_weights(args);
+    // default 30 expressions per page
+    args.sheet.write(&args.layout, None, "./output/add-minus.docx", Source::new("add-minus", args), |rng| Ok(vec![gen_section(args, None, rng)?]))
+}
+
+pub fn gen_section(args: &AddMinusOpts, heading: Option<String>, rng: &mut impl Rng) -> Result<Section, Error> {
+    Ok(Section {
+        heading,
+        problems: gen_problems(args, rng)?,
+        columns: args.column_per_page,
+        font_size: args.output_docx_font_size as usize,
+        spaced: false,
+        answer_style: args.layout.answer_style,
+    })
+}
+
+// 小节的默认标题
+pub fn default_heading(args: &AddMinusOpts) -> String {
+    let c = &args.category;
+    if c.starts_with("+") || c.ends_with("p1") {
+        t("heading.add")
+    } else if c.starts_with("_") || c.ends_with("p2") || c.ends_with("p3") || c.ends_with("p4") {
+        t("heading.minus")
+    } else {
+        t("heading.mixed")
+    }
+}
+
+fn gen_problems_by_pattern2(args: &AddMinusOpts, rng: &mut impl Rng) -> Vec<Problem> {
     let map_pair: HashMap<u32, Vec<(u32, u32)>> = new_hashmap_for_pattern2();
-    gen_arithmetic_to_docx_by_pattern2_3_4(args, &map_pair);
+    gen_problems_by_pattern2_3_4(args, &map_pair, rng)
 }
 fn new_hashmap_for_pattern2() -> HashMap<u32, Vec<(u32, u32)>> {
     let mut map_pair: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
@@ -70,10 +289,15 @@ fn new_hashmap_for_pattern2() -> HashMap<u32, Vec<(u32, u32)>> {
     map_pair
 }
 
-pub fn gen_arithmetic_to_docx_by_pattern3(args: &AddMinusOpts) {
+fn new_hashmap_for_pattern3() -> HashMap<u32, Vec<(u32, u32)>> {
     let mut map_pair: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
     map_pair.insert(11, vec![(11, 7), (11, 6), (11, 5), (11, 4), (11, 3), (11, 2)]);
     map_pair.insert(10, vec![(10, 9), (10, 8), (10, 7), (10, 6), (10, 5), (10, 4), (10, 3), (10, 2), (10, 1)]);
+    map_pair
+}
+
+fn gen_problems_by_pattern3(args: &AddMinusOpts, rng: &mut impl Rng) -> Vec<Problem> {
+    let mut map_pair = new_hashmap_for_pattern3();
 
     // extract 15 expressions randomly from 12~17
     let mut map_freq: HashMap<u32, usize> = HashMap::new();
@@ -84,12 +308,15 @@ pub fn gen_arithmetic_to_docx_by_pattern3(args: &AddMinusOpts) {
     map_freq.insert(13, 3);
     map_freq.insert(12, 4);
     let mut map_patter2 = new_hashmap_for_pattern2();
-    map_patter2.retain(|k, v| map_freq.contains_key(k));
+    map_patter2.retain(|k, _| map_freq.contains_key(k));
 
-    for &key in map_patter2.keys() {
+    // 按固定顺序取随机数，保证相同的种子生成相同的算式
+    let mut keys: Vec<u32> = map_patter2.keys().copied().collect();
+    keys.sort();
+    for key in keys {
         let &pairs = &map_patter2.get(&key).unwrap();
         let &expected_count = map_freq.get(&key).unwrap();
-        let selected_pairs = pairs.iter().choose_multiple(&mut rand::rng(), expected_count);
+        let selected_pairs = pairs.iter().choose_multiple(rng, expected_count);
         let mut vec:Vec<(u32, u32)> = Vec::new();
         for &selected_pair in selected_pairs {
             vec.push(selected_pair);
@@ -97,9 +324,12 @@ pub fn gen_arithmetic_to_docx_by_pattern3(args: &AddMinusOpts) {
         map_pair.insert(key, vec);
     }
 
-    gen_arithmetic_to_docx_by_pattern2_3_4(args, &map_pair);
+    gen_problems_by_pattern2_3_4(args, &map_pair, rng)
+}
+fn gen_problems_by_pattern4(args: &AddMinusOpts, rng: &mut impl Rng) -> Vec<Problem> {
+    gen_problems_by_pattern2_3_4(args, &new_hashmap_for_pattern4(), rng)
 }
-pub fn gen_arithmetic_to_docx_by_pattern4(args: &AddMinusOpts) {
+fn new_hashmap_for_pattern4() -> HashMap<u32, Vec<(u32, u32)>> {
     let mut map_pair: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
     map_pair.insert(9, vec![(9, 8), (9, 7), (9, 6), (9, 5), (9, 4), (9, 3), (9, 2), (9, 1)]);
     map_pair.insert(8, vec![(8, 7), (8, 6), (8, 5), (8, 4), (8, 3), (8, 2), (8, 1)]);
@@ -107,54 +337,32 @@ pub fn gen_arithmetic_to_docx_by_pattern4(args: &AddMinusOpts) {
     map_pair.insert(6, vec![(6, 5), (6, 4), (6, 3), (6, 2)]);
     map_pair.insert(5, vec![(5, 4), (5, 3), (5, 2)]);
     map_pair.insert(4, vec![(4, 3), (4, 2)]);
-
-    gen_arithmetic_to_docx_by_pattern2_3_4(args, &map_pair);
+    map_pair
 }
 
-pub fn gen_arithmetic_to_docx_by_pattern2_3_4(args: &AddMinusOpts, map_pair: &HashMap<u32, Vec<(u32, u32)>>) {
-    let mut doc = read_from_docx("./resources/template.docx");
-
-    let mut line = String::new();
-    let mut i = 0;
+fn gen_problems_by_pattern2_3_4(args: &AddMinusOpts, map_pair: &HashMap<u32, Vec<(u32, u32)>>, rng: &mut impl Rng) -> Vec<Problem> {
+    let mut problems = vec![];
 
     let mut keys: Vec<_> = map_pair.keys().collect();
     keys.sort_by(|a, b| b.cmp(a));
 
-    while i < args.count {
-        let mut result_pairs: Vec<String> = Vec::new();
+    while problems.len() < args.count as usize {
+        let mut result_pairs: Vec<Problem> = Vec::new();
         for key in &keys {
             for &pair in map_pair.get(key).unwrap().iter() {
-                result_pairs.push(format!("{:>2} - {:<2}=", pair.0, pair.1));
+                result_pairs.push(Problem::expr(format!("{:>2} - {:<2}=", pair.0, pair.1), (pair.0 - pair.1).to_string())
+                    .with_fact(pair.0 as i64, Op::Minus, pair.1 as i64));
             }
         }
         // 打乱顺序
-        result_pairs.shuffle(&mut rand::rng());
-        for pair in result_pairs.iter() {
-            line.push_str(pair);
-
-            i = i + 1;
-            if i > args.count { break }
-            // 可能多个算式组合成一行，写入docx
-            if i % args.column_per_page == 0 || i == args.count {
-                // write paragraph
-                doc = add_paragraph(doc, args.output_docx_font_size as usize, &line);
-
-                line.clear();
-            } else {
-                // 算式之间添加分隔
-                line.push_str("      ");
-            }
-        }
+        result_pairs.shuffle(rng);
+        result_pairs.truncate(args.count as usize - problems.len());
+        problems.append(&mut result_pairs);
     }
-    write_to_docx(doc, "./output/add-minus.docx");
+    problems
 }
 
-pub fn gen_arithmetic_to_docx_by_pattern1(args: &AddMinusOpts) {
-    let mut doc = read_from_docx("./resources/template.docx");
-
-    let mut line = String::new();
-    let mut i = 0;
-    const NUMBER_PER_PAGE: u8 = 26;
+fn new_hashmaps_for_pattern1() -> (PairMap, HashMap<u32, usize>) {
     let mut map_pair: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
     let mut map_freq: HashMap<u32, usize> = HashMap::new();
 
@@ -196,169 +404,265 @@ pub fn gen_arithmetic_to_docx_by_pattern1(args: &AddMinusOpts) {
 
     map_pair.insert(6, vec![(5, 1), (3, 3), (4, 1), (3, 2)]);
     map_freq.insert(6, 1);
+    (map_pair, map_freq)
+}
+
+fn gen_problems_by_pattern1(args: &AddMinusOpts, rng: &mut impl Rng) -> Vec<Problem> {
+    let mut problems = vec![];
+    let (map_pair, map_freq) = new_hashmaps_for_pattern1();
 
     let mut keys: Vec<_> = map_pair.keys().collect();
     keys.sort_by(|a, b| b.cmp(a));
 
-    while i < args.count {
-        let mut result_pairs: Vec<String> = Vec::new();
+    while problems.len() < args.count as usize {
+        let mut result_pairs: Vec<Problem> = Vec::new();
         for key in &keys {
             let pairs = map_pair.get(key).unwrap();
             let &expected_count = map_freq.get(key).unwrap();
-            let selected_pairs = pairs.iter().choose_multiple(&mut rand::rng(), expected_count);
+            let selected_pairs = pairs.iter().choose_multiple(rng, expected_count);
             for &pair in selected_pairs {
                 // 随机决定前后顺序
-                let rand_bool = rand::rng().gen_bool(0.5);
-                if rand_bool {
-                    result_pairs.push(format!("{:>1} + {:<1}=", pair.0, pair.1));
-                } else {
-                    result_pairs.push(format!("{:>1} + {:<1}=", pair.1, pair.0));
-                }
+                let (l, r) = if rng.random_bool(0.5) { pair } else { (pair.1, pair.0) };
+                result_pairs.push(Problem::expr(format!("{:>1} + {:<1}=", l, r), (l + r).to_string())
+                    .with_fact(l as i64, Op::Add, r as i64));
             }
         }
         // 打乱顺序
-        result_pairs.shuffle(&mut rand::rng());
-        for pair in result_pairs.iter() {
-            line.push_str(pair);
-
-            i = i + 1;
-            if i > args.count { break }
-            // 可能多个算式组合成一行，写入docx
-            if i % args.column_per_page == 0 || i == args.count {
-                // write paragraph
-                doc = add_paragraph(doc, args.output_docx_font_size as usize, &line);
-
-                line.clear();
-            } else {
-                // 算式之间添加分隔
-                line.push_str("      ");
+        result_pairs.shuffle(rng);
+        result_pairs.truncate(args.count as usize - problems.len());
+        problems.append(&mut result_pairs);
+    }
+    problems
+}
+
+// p1~p4 中所有的加减法事实，按 p1, p4, p3, p2 先易后难的顺序，每组中从小到大
+pub fn pattern_facts() -> Vec<Fact> {
+    let mut facts = vec![];
+    let tables = [
+        (new_hashmaps_for_pattern1().0, Op::Add),
+        (new_hashmap_for_pattern4(), Op::Minus),
+        (new_hashmap_for_pattern3(), Op::Minus),
+        (new_hashmap_for_pattern2(), Op::Minus),
+    ];
+    for (map_pair, op) in tables {
+        let mut keys: Vec<_> = map_pair.keys().collect();
+        keys.sort();
+        for key in keys {
+            let mut pairs = map_pair.get(key).unwrap().clone();
+            pairs.sort();
+            for (l, r) in pairs {
+                let fact = Fact { left: l as i64, op, right: r as i64 };
+                if !facts.contains(&fact) {
+                    facts.push(fact);
+                }
             }
         }
     }
-    write_to_docx(doc, "./output/add-minus.docx");
+    facts
 }
 
-pub fn gen_arithmetic_to_docx(args: &AddMinusOpts) {
-    let parsed_args = parse_args(args);
-    let mut doc = read_from_docx("./resources/template.docx");
-
-    let mut line = String::new();
-    for i in 1 ..= args.count {
-        // 指定或随机生成算式
-        line.push_str(&gen_arithmetic_expr(&parsed_args));
-
-        // 可能多个算式组合成一行，写入docx
-        if i % args.column_per_page == 0 || i == args.count {
-            // write paragraph
-            doc = add_paragraph(doc, args.output_docx_font_size as usize, &line);
+// p1~p4 中的分与合：(整体, 部分, 部分)，加法的整体是两数之和，减法的整体是被减数，从小到大
+pub fn preset_bonds(preset: Preset) -> Vec<(u32, u32, u32)> {
+    let (map_pair, op) = match preset {
+        Preset::P1 => (new_hashmaps_for_pattern1().0, Op::Add),
+        Preset::P2 => (new_hashmap_for_pattern2(), Op::Minus),
+        Preset::P3 => (new_hashmap_for_pattern3(), Op::Minus),
+        Preset::P4 => (new_hashmap_for_pattern4(), Op::Minus),
+    };
+    let mut bonds: Vec<(u32, u32, u32)> = map_pair.values().flatten()
+        .map(|&(l, r)| if op == Op::Add { (l + r, l, r) } else { (l, r, l - r) })
+        .collect();
+    bonds.sort();
+    bonds
+}
 
-            line.clear();
-        } else {
-            // 算式之间添加分隔
-            line.push_str("      ");
-        }
-    }
-    write_to_docx(doc, "./output/add-minus.docx");
+// 由加减法事实生成算式，与 p1~p4 的格式一致
+pub fn fact_problem(fact: &Fact) -> Problem {
+    let (l, r) = (fact.left, fact.right);
+    let answer = match fact.op {
+        Op::Minus => l - r,
+        _ => l + r,
+    };
+    let width = l.max(r).to_string().len();
+    Problem::expr(format!("{:>w$} {} {:<w$}=", l, fact.op.symbol(), r, w = width), answer.to_string())
+        .with_fact(l, fact.op, r)
 }
 
 // 根据指定条件或随机生成算式
-fn gen_arithmetic_expr(args: &ParsedArgs) -> String {
+fn gen_arithmetic_expr(args: &ParsedArgs, rng: &mut impl Rng) -> Result<Problem, Error> {
     let c = &args.origin.category;
-    if c.starts_with("+") {
-        gen_add(args)
+    if args.origin.change {
+        // 找零总是减法
+        gen_minus(args, rng)
+    } else if c.starts_with("+") {
+        gen_add(args, rng)
     } else if c.starts_with("_") {
-        gen_minus(args)
+        gen_minus(args, rng)
     } else /*if c == 'x'*/ {
-        if rand::random() {
-            gen_add(args)
+        if rng.random() {
+            gen_add(args, rng)
         } else {
-            gen_minus(args)
+            gen_minus(args, rng)
         }
     }
 }
 
-enum Op {
-    Add, Minus, Mul, Div
+fn gen_add(args: &ParsedArgs, rng: &mut impl Rng) -> Result<Problem, Error> {
+    gen_binary(args, Op::Add, rng)
+}
+fn gen_minus(args: &ParsedArgs, rng: &mut impl Rng) -> Result<Problem, Error> {
+    gen_binary(args, Op::Minus, rng)
+}
+
+// 运算结果，溢出 i64 时为 None
+fn checked_result(op: Op, l: i64, r: i64) -> Option<i64> {
+    match op {
+        Op::Add => l.checked_add(r),
+        Op::Minus => l.checked_sub(r),
+        Op::Mul => l.checked_mul(r),
+        Op::Div => l.checked_div(r),
+    }
+}
+
+// 负数作为右操作数时加括号，比如 -7 - (-3)
+fn operand_text(number: i64, right: bool) -> String {
+    if right && number < 0 { format!("({})", number) } else { number.to_string() }
+}
+
+// 其他生成器只需要 -p、数的范围和结果范围，其余参数不影响运算数
+pub fn operand_opts(pattern: String, number: RangeInclusive<i64>, result: RangeInclusive<i64>) -> AddMinusOpts {
+    AddMinusOpts {
+        count: 1,
+        operand_pattern: pattern,
+        number_min_inclusive: *number.start(),
+        number_max_inclusive: *number.end(),
+        result_min_inclusive: *result.start(),
+        result_max_inclusive: *result.end(),
+        ..Default::default()
+    }
 }
 
-fn gen_add(args: &ParsedArgs) -> String {
-    let is_valid = |p: (u16, u16)| {
-        let ans = p.0 as i16 + p.1 as i16;
-        args.origin.result_min_inclusive <= ans && ans <= args.origin.result_max_inclusive
+// 按 args 的数的范围、-p 生成运算数，结果在 [-b, -e] 中并且满足 accept，供其他生成器使用
+pub fn gen_operand_pair<F: Fn((i64, i64)) -> bool>(args: &AddMinusOpts, op: Op, accept: F, rng: &mut impl Rng) -> Result<(i64, i64), Error> {
+    gen_valid_operands(&parse_args(args)?, op, accept, rng)
+}
+
+fn gen_valid_operands<F: Fn((i64, i64)) -> bool>(args: &ParsedArgs, op: Op, accept: F, rng: &mut impl Rng) -> Result<(i64, i64), Error> {
+    let is_valid = |p: (i64, i64)| {
+        checked_result(op, p.0, p.1).is_some_and(|ans| args.origin.result_min_inclusive <= ans && ans <= args.origin.result_max_inclusive) && accept(p)
     };
-    let pair = gen_operands(args, Op::Add, is_valid);
-    let width = char_len(args.origin.number_max_inclusive);
-    if width < 2 {
-        format!("{:>1} + {:<1}=", pair.0, pair.1)
-    } else if width == 2 {
-        format!("{:>2} + {:<2}=", pair.0, pair.1)
-    } else {
-        format!("{:>3} + {:<3}=", pair.0, pair.1)
+    gen_operands(args, op, is_valid, rng)
+}
+
+// 按范围内最宽的数对齐，左操作数右对齐，右操作数左对齐
+fn gen_binary(args: &ParsedArgs, op: Op, rng: &mut impl Rng) -> Result<Problem, Error> {
+    let (l, r) = gen_valid_operands(args, op, |_| true, rng)?;
+    if let Some(currency) = args.origin.money {
+        return Ok(money_problem(args, currency, op, l, r));
+    }
+    if args.origin.decimals.is_some() {
+        return Ok(decimal_problem(args, op, l, r));
     }
+    let width = [args.origin.number_min_inclusive, args.origin.number_max_inclusive].iter()
+        .map(|&n| operand_text(n, true).len())
+        .max().unwrap();
+    // 已经校验过不会溢出
+    let answer = checked_result(op, l, r).unwrap().to_string();
+    Ok(Problem::expr(format!("{:>w$} {} {:<w$}=", l, op.symbol(), operand_text(r, true), w = width), answer)
+        .with_fact(l, op, r))
 }
-fn gen_minus(args: &ParsedArgs) -> String {
-    let is_valid = |p: (u16, u16)| {
-        let ans = p.0 as i16 - p.1 as i16;
-        args.origin.result_min_inclusive <= ans && ans <= args.origin.result_max_inclusive
+
+// 小数算式，左右操作数都右对齐，同一列中的小数点上下对齐
+// 小数不是加减法事实，不记录 fact
+fn decimal_problem(args: &ParsedArgs, op: Op, l: i64, r: i64) -> Problem {
+    let scale = args.scale();
+    let (left_places, right_places) = args.decimals;
+    let text = |n: i64, places: u32, right: bool| {
+        let text = decimal_text(n, scale, places);
+        if right && n < 0 { format!("({})", text) } else { text }
     };
-    let pair = gen_operands(args, Op::Minus, is_valid);
-    let width = char_len(args.origin.number_max_inclusive);
-    if width < 2 {
-        format!("{:>1} - {:<1}=", pair.0, pair.1)
-    } else if width == 2 {
-        format!("{:>2} - {:<2}=", pair.0, pair.1)
-    } else {
-        format!("{:>3} - {:<3}=", pair.0, pair.1)
+    let width = |places: u32, right: bool| [args.origin.number_min_inclusive, args.origin.number_max_inclusive].iter()
+        .map(|&n| text(n - n % args.unit(places), places, right).len())
+        .max().unwrap();
+    let answer = decimal_text(checked_result(op, l, r).unwrap(), scale, scale);
+    Problem::expr(format!("{:>wl$} {} {:>wr$} =", text(l, left_places, false), op.symbol(), text(r, right_places, true),
+                          wl = width(left_places, false), wr = width(right_places, true)), answer)
+}
+
+// 金额算式，操作数和答案都写成金额，复名数长短不一，不对齐
+// 金额不是加减法事实，不记录 fact
+fn money_problem(args: &ParsedArgs, currency: Currency, op: Op, l: i64, r: i64) -> Problem {
+    let text = |n: i64, right: bool| {
+        let text = if args.origin.money_units { currency.units_text(n) } else { currency.amount_text(n) };
+        if right && n < 0 { format!("({})", text) } else { text }
+    };
+    let answer = text(checked_result(op, l, r).unwrap(), false);
+    if args.origin.money_units {
+        return Problem::expr(format!("{} {} {} =", text(l, false), op.symbol(), text(r, true)), answer);
     }
+    let notes = args.change_notes().unwrap_or_default();
+    let width = |right: bool| [args.origin.number_min_inclusive, args.origin.number_max_inclusive].iter().chain(&notes)
+        .map(|&n| text(n, right).chars().count())
+        .max().unwrap();
+    Problem::expr(format!("{:>wl$} {} {:>wr$} =", text(l, false), op.symbol(), text(r, true), wl = width(false), wr = width(true)), answer)
 }
 
-fn gen_operands<F: Fn((u16, u16)) -> bool>(args: &ParsedArgs, op: Op, is_valid: F) -> (u16, u16) {
+// 随机生成满足条件的运算数，-l 大于 -r 或者尝试 MAX_ATTEMPTS 次都不满足条件时返回错误
+fn gen_operands<F: Fn((i64, i64)) -> bool>(args: &ParsedArgs, op: Op, is_valid: F, rng: &mut impl Rng) -> Result<(i64, i64), Error> {
     let min = args.origin.number_min_inclusive;
     let max = args.origin.number_max_inclusive;
-    let range = min..max + 1;
+    let range = min..=max;
 
-    let mut rng = rng();
-    let uniform = Uniform::new(min, max + 1).unwrap();
+    let uniform = Uniform::new_inclusive(min, max)
+        .map_err(|_| Error::Parse(tf("error.number_range", &[("min", &min), ("max", &max)])))?;
 
-    loop {
-        let mut l = rng.sample(uniform);
-        let mut r = rng.sample(uniform);
+    let (unit_l, unit_r) = args.units();
+    let notes = args.change_notes();
+    for _ in 0..MAX_ATTEMPTS {
+        // 小数位数较少的操作数取最接近的单位的倍数
+        let mut l = utils::round_to(rng.sample(uniform), unit_l, &range);
+        let mut r = utils::round_to(rng.sample(uniform), unit_r, &range);
         match &args.operand_config {
             OperandConfig::TwoOperand(pattern_l, pattern_r) => {
                 // L, R
-                l = parse_number_by_pattern(pattern_l, l, &range);
-                r = parse_number_by_pattern(pattern_r, r, &range);
+                l = parse_number_by_pattern(pattern_l, l, &range, rng);
+                r = parse_number_by_pattern(pattern_r, r, &range, rng);
             }
             OperandConfig::OneOperand(pattern_l) => {
                 // L,L
-                l = parse_number_by_pattern(pattern_l, l, &range);
+                l = parse_number_by_pattern(pattern_l, l, &range, rng);
                 r = l;
             }
             OperandConfig::Result(ans) => {
                 // =A
                 match op {
                     // min<= r=ans-l <=max
-                    Op::Add => {
-                        if l + max < *ans || l + min > *ans { continue }
-                        r = ans - l
+                    Op::Add => match ans.checked_sub(l) {
+                        Some(diff) if range.contains(&diff) => r = diff,
+                        _ => continue,
                     },
                     // min <= r=l-ans <= max
-                    Op::Minus => {
-                        if ans + max < l || ans + min > l  { continue }
-                        r = l - ans;
-                    }
+                    Op::Minus => match l.checked_sub(*ans) {
+                        Some(diff) if range.contains(&diff) => r = diff,
+                        _ => continue,
+                    },
                     _ => {
                     }
                 }
             }
         }
 
-        if !is_valid((l, r)) {  continue }
-        return (l, r);
+        if let Some(notes) = &notes {
+            l = *notes.choose(rng).unwrap();
+        }
+
+        if l % unit_l != 0 || r % unit_r != 0 || !is_valid((l, r)) {  continue }
+        return Ok((l, r));
     }
+    Err(Error::Parse(tf("error.no_operands", &[("pattern", &args.origin.operand_pattern)])))
 }
 
-fn parse_number_by_pattern(pattern: &OperandPattern, ans: u16, range: &Range<u16>) -> u16 {
+fn parse_number_by_pattern(pattern: &OperandPattern, ans: i64, range: &RangeInclusive<i64>, rng: &mut impl Rng) -> i64 {
     // let ans = rand::rng().gen_range(min..max + 1);
     match pattern {
         OperandPattern::Wildcard => { ans }
@@ -375,7 +679,7 @@ fn parse_number_by_pattern(pattern: &OperandPattern, ans: u16, range: &Range<u16
         }
         OperandPattern::ConstantRange(r) => {
             //忽略ans, 指定常数范围不受min~max范围限制
-            rng().random_range(r.start..r.end)
+            rng.random_range(r.clone())
         }
     }
 }
@@ -384,41 +688,43 @@ fn parse_number_by_pattern(pattern: &OperandPattern, ans: u16, range: &Range<u16
 fn gen_arithmetic_to_txt(args: &AddMinusOpts) {
     // let mut line: Vec<String> = vec![];
     let mut lines = String::new();
+    let mut rng = new_rng(args.sheet.seed);
     for i in 1 ..= args.count {
         // 指定或随机生成加法
-        lines.push_str(&gen_arithmetic_expr(&parse_args(args)));
+        lines.push_str(&render_text(&gen_arithmetic_expr(&parse_args(args).expect("valid args"), &mut rng).expect("valid args"), &args.layout.answer_style, false));
 
         if i == args.count {
             break;
         }
 
         if i % args.column_per_page == 0 {
-            lines.push_str("\n");
+            lines.push('\n');
         } else {
             lines.push_str("      ");
         }
     }
-    write(&lines.trim_end(), "./output/add-minus.txt").expect("Write error!");
+    write(lines.trim_end(), "./output/add-minus.txt").expect("Write error!");
     println!("Generate Add/Minus successfully")
 }
 
 #[cfg(test)]
 mod test{
-    use crate::add_minus::{gen_add, gen_arithmetic_to_txt, parse_args};
-    use crate::{AddMinusOpts};
+    use crate::add_minus::{decimal_text, fact_problem, gen_add, gen_arithmetic_to_txt, gen_minus, gen_problems, parse_args, parse_number_by_pattern, pattern_facts, validate_decimals, validate_operand_pattern};
+    use crate::problem::{Fact, Op};
+    use crate::{AddMinusOpts, Currency, OperandPattern};
+    use crate::utils::new_rng;
 
     #[test]
     fn test_gen_arithmetic() {
         let args = AddMinusOpts {
             count: 40,
             column_per_page: 2,
-            number_min_inclusive: 0,
             number_max_inclusive: 10,
-            result_min_inclusive: 0,
             category: "+".to_string(),
             output_docx_font_size: 56,
             operand_pattern: "*,*".to_string(),
             result_max_inclusive: 99,
+            ..Default::default()
         };
         gen_arithmetic_to_txt(&args);
     }
@@ -428,15 +734,244 @@ mod test{
         let args = AddMinusOpts {
             count: 40,
             column_per_page: 2,
-            number_min_inclusive: 0,
             number_max_inclusive: 10,
-            result_min_inclusive: 0,
             category: "+".to_string(),
             output_docx_font_size: 56,
             operand_pattern: "*,*".to_string(),
             result_max_inclusive: 99,
+            ..Default::default()
         };
-        let s = gen_add(&parse_args(&args));
+        let s = gen_add(&parse_args(&args).unwrap(), &mut new_rng(None)).unwrap();
         println!("{:?}", s);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_gen_large_numbers() {
+        let args = AddMinusOpts {
+            count: 40,
+            column_per_page: 2,
+            number_min_inclusive: 1_000_000,
+            number_max_inclusive: 9_999_999,
+            category: "x".to_string(),
+            output_docx_font_size: 56,
+            operand_pattern: "*,*".to_string(),
+            result_max_inclusive: i64::MAX,
+            ..Default::default()
+        };
+        let mut rng = new_rng(Some(1));
+        let add = gen_add(&parse_args(&args).unwrap(), &mut rng).unwrap();
+        let fact = add.fact.unwrap();
+        assert_eq!(add.answers, vec![(fact.left + fact.right).to_string()]);
+        // 按7位数对齐
+        assert_eq!(add.solved_text(), format!("{:>7} + {:<7}= {}", fact.left, fact.right, fact.left + fact.right));
+        let minus = gen_minus(&parse_args(&args).unwrap(), &mut rng).unwrap();
+        let fact = minus.fact.unwrap();
+        assert!(fact.left >= fact.right);
+        assert_eq!(minus.answers, vec![(fact.left - fact.right).to_string()]);
+
+        // 接近 i64 上限时减法不会溢出
+        let max = i64::MAX;
+        let args = AddMinusOpts { number_min_inclusive: max - 10, number_max_inclusive: max, ..args };
+        let minus = gen_minus(&parse_args(&args).unwrap(), &mut rng).unwrap();
+        let fact = minus.fact.unwrap();
+        assert_eq!(minus.answers, vec![(fact.left - fact.right).to_string()]);
+    }
+
+    #[test]
+    fn test_gen_negative_numbers() {
+        let args = AddMinusOpts {
+            count: 40,
+            column_per_page: 2,
+            number_min_inclusive: -20,
+            number_max_inclusive: 20,
+            result_min_inclusive: -40,
+            category: "_".to_string(),
+            output_docx_font_size: 56,
+            operand_pattern: "*,-9~-1".to_string(),
+            result_max_inclusive: 40,
+            ..Default::default()
+        };
+        for problem in gen_problems(&args, &mut new_rng(Some(5))).unwrap() {
+            let fact = problem.fact.unwrap();
+            assert!((-9..=-1).contains(&fact.right));
+            assert_eq!(problem.answers, vec![(fact.left - fact.right).to_string()]);
+            // 负的右操作数加括号，按最宽的 (-20) 对齐
+            assert_eq!(problem.solved_text(), format!("{:>5} - {:<5}= {}", fact.left, format!("({})", fact.right), fact.left - fact.right));
+        }
+    }
+
+    #[test]
+    fn test_gen_decimals() {
+        assert_eq!(decimal_text(345, 2, 2), "3.45");
+        assert_eq!(decimal_text(170, 2, 1), "1.7");
+        assert_eq!(decimal_text(-5, 2, 2), "-0.05");
+        assert_eq!(decimal_text(500, 2, 0), "5");
+        assert!(validate_decimals("2").is_ok() && validate_decimals("2,1").is_ok());
+        assert!(validate_decimals("2,1,0").is_err() && validate_decimals("10").is_err() && validate_decimals("x").is_err());
+
+        // 3.45 + 1.7 这样的两位小数加一位小数，右操作数是 0.1 的倍数，按 0.5 的倍数出题
+        let args = AddMinusOpts {
+            count: 40,
+            column_per_page: 2,
+            number_max_inclusive: 999,
+            category: "x".to_string(),
+            output_docx_font_size: 56,
+            operand_pattern: "*,50*".to_string(),
+            decimals: Some("2,1".to_string()),
+            result_max_inclusive: 1500,
+            ..Default::default()
+        };
+        for problem in gen_problems(&args, &mut new_rng(Some(9))).unwrap() {
+            let text = problem.solved_text();
+            let (question, answer) = text.split_once("= ").unwrap();
+            let tokens: Vec<&str> = question.split_whitespace().collect();
+            let (l, r): (f64, f64) = (tokens[0].parse().unwrap(), tokens[2].parse().unwrap());
+            assert_eq!(tokens[0].split_once('.').unwrap().1.len(), 2);
+            assert_eq!(tokens[2].split_once('.').unwrap().1.len(), 1);
+            assert!(tokens[2].ends_with(".0") || tokens[2].ends_with(".5"));
+            // 小数点对齐：左右操作数宽度固定
+            assert_eq!(question.find('.'), Some(1));
+            assert_eq!(question.rfind('.'), Some(8));
+            let expected = if tokens[1] == "+" { l + r } else { l - r };
+            assert!(((answer.parse::<f64>().unwrap()) - expected).abs() < 1e-9);
+            assert!(problem.fact.is_none());
+        }
+    }
+
+    #[test]
+    fn test_gen_money() {
+        // 不超过 ¥20.00 的金额，结果不超过 ¥30.00，都以分计
+        let args = AddMinusOpts {
+            count: 40,
+            column_per_page: 2,
+            number_max_inclusive: 2000,
+            category: "x".to_string(),
+            output_docx_font_size: 56,
+            operand_pattern: "*,*".to_string(),
+            money: Some(Currency::Cny),
+            result_max_inclusive: 3000,
+            ..Default::default()
+        };
+        let amount = |s: &str| (s.trim().trim_start_matches('¥').parse::<f64>().unwrap() * 100.0).round() as i64;
+        for problem in gen_problems(&args, &mut new_rng(Some(5))).unwrap() {
+            let text = problem.solved_text();
+            let (question, answer) = text.split_once("= ").unwrap();
+            let tokens: Vec<&str> = question.split_whitespace().collect();
+            let (l, r) = (amount(tokens[0]), amount(tokens[2]));
+            // 金额是 1角的倍数，右对齐
+            assert!(l % 10 == 0 && r % 10 == 0 && l <= 2000 && r <= 2000);
+            assert_eq!(question.chars().count(), "¥20.00 + ¥20.00 ".chars().count());
+            let expected = if tokens[1] == "+" { l + r } else { l - r };
+            assert_eq!(amount(answer), expected);
+            assert!((0..=3000).contains(&expected));
+            assert!(problem.fact.is_none());
+        }
+
+        // 找零：被减数是纸币，答案写成复名数
+        let args = AddMinusOpts { category: "+".to_string(), change: true, money_units: true, number_max_inclusive: 1000, result_max_inclusive: 5000, ..args };
+        let problems = gen_problems(&args, &mut new_rng(Some(6))).unwrap();
+        for problem in &problems {
+            let text = problem.solved_text();
+            let (question, answer) = text.split_once(" = ").unwrap();
+            let (note, _) = question.split_once(" - ").unwrap();
+            assert!(["1元", "5元", "10元", "20元", "50元"].contains(&note), "{}", text);
+            assert!(answer.ends_with('元') || answer.ends_with('角'));
+        }
+        assert!(problems.iter().any(|p| p.solved_text().contains("元") && p.solved_text().contains("角")));
+    }
+
+    #[test]
+    fn test_gen_problems_by_pattern() {
+        for category in ["p1", "p2", "p3", "p4"] {
+            let args = AddMinusOpts {
+                count: 45,
+                column_per_page: 3,
+                number_max_inclusive: 10,
+                category: category.to_string(),
+                output_docx_font_size: 56,
+                operand_pattern: "*,*".to_string(),
+                result_max_inclusive: 99,
+                ..Default::default()
+            };
+            let problems = gen_problems(&args, &mut new_rng(Some(7))).unwrap();
+            assert_eq!(problems.len(), 45);
+            assert!(problems.iter().all(|p| p.answers.len() == 1));
+            // 相同的种子生成相同的算式
+            assert_eq!(problems, gen_problems(&args, &mut new_rng(Some(7))).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_gen_problems_invalid() {
+        let mut args = AddMinusOpts {
+            count: 5,
+            column_per_page: 3,
+            number_min_inclusive: 10,
+            number_max_inclusive: 5,
+            category: "+".to_string(),
+            output_docx_font_size: 56,
+            operand_pattern: "*,*".to_string(),
+            result_max_inclusive: 99,
+            ..Default::default()
+        };
+        // 最小值大于最大值
+        let error = gen_problems(&args, &mut new_rng(Some(8))).unwrap_err();
+        assert!(error.to_string().contains("最小值 10 大于最大值 5"));
+        // 结果范围内没有满足模式的算式
+        args.number_min_inclusive = 0;
+        args.operand_pattern = "=200".to_string();
+        let error = gen_problems(&args, &mut new_rng(Some(8))).unwrap_err();
+        assert!(error.to_string().contains("'=200'"));
+        // 两位小数和一位小数相加时右操作数必须是 10 的倍数，不能卡住
+        args.operand_pattern = "*,5".to_string();
+        args.decimals = Some("2,1".to_string());
+        let error = gen_problems(&args, &mut new_rng(Some(8))).unwrap_err();
+        assert!(error.to_string().contains("1 和 10 的倍数"));
+        for pattern in ["*,20", "*,30*", "*,5~15"] {
+            args.operand_pattern = pattern.to_string();
+            assert_eq!(gen_problems(&args, &mut new_rng(Some(8))).unwrap().len(), 5);
+        }
+        args.operand_pattern = "*,11~19".to_string();
+        assert!(gen_problems(&args, &mut new_rng(Some(8))).is_err());
+    }
+
+    #[test]
+    fn test_pattern_facts() {
+        let facts = pattern_facts();
+        assert_eq!(facts[0], Fact { left: 3, op: Op::Add, right: 2 });
+        assert!(facts.contains(&Fact { left: 9, op: Op::Add, right: 9 }));
+        assert!(facts.contains(&Fact { left: 18, op: Op::Minus, right: 9 }));
+        // p2 和 p3 共用 12~17 的减法，不重复
+        assert_eq!(facts.iter().filter(|f| **f == Fact { left: 12, op: Op::Minus, right: 3 }).count(), 1);
+
+        assert_eq!(fact_problem(&Fact { left: 9, op: Op::Add, right: 7 }).solved_text(), "9 + 7= 16");
+        assert_eq!(fact_problem(&Fact { left: 13, op: Op::Minus, right: 5 }).solved_text(), "13 - 5 = 8");
+    }
+
+    #[test]
+    fn test_parse_number_by_pattern() {
+        let mut rng = new_rng(Some(1));
+        // -p 10* 时恰好在中间的数取较小的倍数
+        assert_eq!(parse_number_by_pattern(&OperandPattern::NumberWildcard(10), 15, &(0..=99), &mut rng), 10);
+        assert_eq!(parse_number_by_pattern(&OperandPattern::NumberWildcard(10), 16, &(0..=99), &mut rng), 20);
+        assert_eq!(parse_number_by_pattern(&OperandPattern::NumberWildcard(10), 30, &(0..=99), &mut rng), 30);
+    }
+
+    #[test]
+    fn test_validate_operand_pattern() {
+        for pattern in ["*,*", "10*,5*", "*", "5*", "=10", "10,*", "3~7,2-4"] {
+            assert_eq!(validate_operand_pattern(pattern).as_deref(), Ok(pattern));
+        }
+        assert!(validate_operand_pattern("1,2,3").unwrap_err().contains("操作数太多"));
+        assert!(validate_operand_pattern("0*").unwrap_err().contains("倍数必须大于 0"));
+        assert!(validate_operand_pattern("7~3").unwrap_err().contains("范围的起点大于终点"));
+        assert!(validate_operand_pattern("=x").unwrap_err().contains("'x' 不是整数"));
+        assert!(validate_operand_pattern("a*,*").is_err());
+        // 负数常数
+        for pattern in ["*,-3", "*,(-3)", "=-4", "-5~5", "-5-5", "(-5)-(-1)", "-9~-1"] {
+            assert_eq!(validate_operand_pattern(pattern).as_deref(), Ok(pattern));
+        }
+        assert!(validate_operand_pattern("-1~-5").unwrap_err().contains("范围的起点大于终点"));
+        assert!(validate_operand_pattern("-5*").is_err());
+    }
+}
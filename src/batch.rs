@@ -0,0 +1,208 @@
+use std::path::Path;
+use clap::{Args, Command, FromArgMatches, Parser};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use crate::i18n::tf;
+use crate::{BatchOpts, LayoutOpts, SectionCli, SheetOpts, WorksheetOpts};
+use crate::err::Error;
+use crate::utils::read;
+
+// 描述文件，包含一份或多份试卷
+#[derive(Deserialize, Debug)]
+struct BatchSpec {
+    #[serde(rename = "worksheet")]
+    worksheets: Vec<WorksheetSpec>,
+}
+
+// 一份试卷：要么用 generator 加上其参数生成单个生成器的试卷，要么用 section 列出多个小节
+#[derive(Deserialize, Debug)]
+struct WorksheetSpec {
+    // 输出文件名，位于 ./output 目录下
+    output: String,
+    title: Option<String>,
+    generator: Option<String>,
+    #[serde(default, rename = "section")]
+    sections: Vec<SectionSpec>,
+    // 其余字段：有 generator 时是生成器的参数，否则是 numbering、seed、copies 等整份试卷的参数
+    #[serde(flatten)]
+    options: Map<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SectionSpec {
+    generator: String,
+    heading: Option<String>,
+    #[serde(flatten)]
+    options: Map<String, Value>,
+}
+
+impl BatchOpts {
+    pub fn gen_batch_to_docx(&self) -> Result<(), Error> {
+        for path in &self.specs {
+            let spec = parse_spec(path)?;
+            for worksheet in &spec.worksheets {
+                worksheet.gen_to_docx()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_spec(path: &Path) -> Result<BatchSpec, Error> {
+    let content = read(path.to_path_buf())?;
+    let spec = if path.extension().is_some_and(|e| e == "json") {
+        serde_json::from_str(&content)?
+    } else {
+        toml::from_str(&content)?
+    };
+    Ok(spec)
+}
+
+impl WorksheetSpec {
+    fn gen_to_docx(&self) -> Result<(), Error> {
+        let (sections, (layout, sheet), default_headings) = match &self.generator {
+            Some(generator) => {
+                if !self.sections.is_empty() {
+                    return Err(Error::Parse(tf("error.batch_both", &[("output", &self.output)])));
+                }
+                let section = SectionCli::try_parse_from(section_args(generator, None, &self.options)?)?;
+                let opts = (section.generator.layout().clone(), section.generator.sheet().clone());
+                (vec![section], opts, false)
+            },
+            None => {
+                if self.sections.is_empty() {
+                    return Err(Error::Parse(tf("error.batch_neither", &[("output", &self.output)])));
+                }
+                let sections = self.sections.iter()
+                    .map(|s| Ok(SectionCli::try_parse_from(section_args(&s.generator, s.heading.as_deref(), &s.options)?)?))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                (sections, parse_sheet_opts(spec_args(&self.options)?)?, true)
+            },
+        };
+
+        let opts = WorksheetOpts {
+            sections: vec![],
+            title: self.title.clone(),
+            output: Path::new("./output").join(&self.output).to_string_lossy().to_string(),
+            layout,
+            sheet,
+        };
+        opts.write_sections(sections, default_headings)
+    }
+}
+
+// 小节对应的命令行：[--heading H] <generator> --key=value ...
+fn section_args(generator: &str, heading: Option<&str>, options: &Map<String, Value>) -> Result<Vec<String>, Error> {
+    let mut args = vec![];
+    if let Some(heading) = heading {
+        args.push(format!("--heading={}", heading));
+    }
+    args.push(generator.to_string());
+    args.append(&mut spec_args(options)?);
+    Ok(args)
+}
+
+// 字段转换为长参数：count = 20 -> --count=20，布尔值为 true 时只保留开关，数组展开为多个参数
+fn spec_args(options: &Map<String, Value>) -> Result<Vec<String>, Error> {
+    let mut args = vec![];
+    for (key, value) in options {
+        let flag = format!("--{}", key.replace('_', "-"));
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Bool(true) => args.push(flag.clone()),
+                Value::Bool(false) | Value::Null => {},
+                Value::Number(n) => args.push(format!("{}={}", flag, n)),
+                Value::String(s) => args.push(format!("{}={}", flag, s)),
+                _ => return Err(Error::Parse(tf("error.unsupported_value", &[("key", &key), ("value", &value)]))),
+            }
+        }
+    }
+    Ok(args)
+}
+
+// 用 clap 解析整份试卷的参数，默认值和校验与命令行完全一致
+fn parse_sheet_opts(args: Vec<String>) -> Result<(LayoutOpts, SheetOpts), Error> {
+    let command = SheetOpts::augment_args(LayoutOpts::augment_args(Command::new("spec").no_binary_name(true)));
+    let matches = command.try_get_matches_from(args)?;
+    Ok((LayoutOpts::from_arg_matches(&matches)?, SheetOpts::from_arg_matches(&matches)?))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Map, Value};
+    use crate::{Generator, NumberStyle};
+    use crate::batch::{parse_sheet_opts, section_args, spec_args, BatchSpec};
+
+    fn map(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_spec_args() {
+        let args = spec_args(&map(json!({"count": 20, "operand_pattern": "10*,10*", "answer_key": true, "start_as_multiple_step": false}))).unwrap();
+        assert_eq!(args, vec!["--answer-key", "--count=20", "--operand-pattern=10*,10*"]);
+        assert!(spec_args(&map(json!({"count": {"a": 1}}))).is_err());
+    }
+
+    #[test]
+    fn test_parse_sheet_opts() {
+        let (layout, sheet) = parse_sheet_opts(spec_args(&map(json!({"numbering": "circled", "seed": 7, "copies": 3}))).unwrap()).unwrap();
+        assert_eq!(layout.numbering, NumberStyle::Circled);
+        assert!(!layout.answer_key);
+        assert_eq!(sheet.seed, Some(7));
+        assert_eq!(sheet.copies, 3);
+        assert!(!sheet.single_file);
+        assert!(parse_sheet_opts(spec_args(&map(json!({"count": 1}))).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_spec() {
+        let spec: BatchSpec = toml::from_str(r#"
+            [[worksheet]]
+            output = "add.docx"
+            generator = "add-minus"
+            category = "_"
+            number_min_inclusive = 8
+            number_max_inclusive = 99
+            result_min_inclusive = -5
+
+            [[worksheet]]
+            output = "daily.docx"
+            seed = 7
+            copies = 3
+            numbering = "arabic"
+            [[worksheet.section]]
+            generator = "missing-number"
+            heading = "找规律"
+            step = -3
+        "#).unwrap();
+        assert_eq!(spec.worksheets.len(), 2);
+        assert_eq!(spec.worksheets[1].options.get("copies"), Some(&json!(3)));
+
+        let w = &spec.worksheets[0];
+        let section = clap::Parser::try_parse_from(section_args(w.generator.as_ref().unwrap(), None, &w.options).unwrap());
+        let section: crate::SectionCli = section.unwrap();
+        match section.generator {
+            Generator::AddMinus(opts) => {
+                assert_eq!(opts.number_min_inclusive, 8);
+                assert_eq!(opts.result_min_inclusive, -5);
+                assert_eq!(opts.count, 40);
+            },
+            _ => panic!("expect add-minus"),
+        }
+
+        let s = &spec.worksheets[1].sections[0];
+        let section: crate::SectionCli = clap::Parser::try_parse_from(section_args(&s.generator, s.heading.as_deref(), &s.options).unwrap()).unwrap();
+        assert_eq!(section.heading.as_deref(), Some("找规律"));
+    }
+
+    #[test]
+    fn test_parse_json_spec() {
+        let spec: BatchSpec = serde_json::from_str(r#"{"worksheet": [{"output": "a.docx", "generator": "add-minus", "category": "+"}]}"#).unwrap();
+        assert_eq!(spec.worksheets[0].options.len(), 1);
+    }
+}
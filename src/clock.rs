@@ -0,0 +1,175 @@
+use std::io::Cursor;
+use docx_rs::{Pic, Run};
+use image::{GrayImage, ImageFormat, Luma};
+
+// 钟面图片的边长(像素)，按 CLOCK_DISPLAY_PIXELS 的大小插入文档，打印时更清晰
+const CLOCK_PIXELS: u32 = 360;
+const CLOCK_DISPLAY_PIXELS: u32 = 150;
+// docx 中 1 像素 = 9525 EMU
+const EMU_PER_PIXEL: u32 = 9525;
+
+// 钟面上的一条线段，坐标以钟面中心为原点、半径为 1，y 轴向下
+struct Stroke {
+    from: (f64, f64),
+    to: (f64, f64),
+    width: f64,
+}
+
+const FACE_RADIUS: f64 = 0.95;
+const FACE_WIDTH: f64 = 0.03;
+const NUMBER_RADIUS: f64 = 0.66;
+const NUMBER_HEIGHT: f64 = 0.16;
+const CENTER_RADIUS: f64 = 0.05;
+
+// 从 12 点方向顺时针转过 degrees 度、距中心 r 的点
+fn point(degrees: f64, r: f64) -> (f64, f64) {
+    let radians = degrees.to_radians();
+    (radians.sin() * r, -radians.cos() * r)
+}
+
+// 刻度，以及 minutes(一天中的第几分钟)对应的时针和分针，None 时是空白钟面
+fn strokes(minutes: Option<u16>) -> Vec<Stroke> {
+    let mut strokes: Vec<Stroke> = (0..60).map(|i| {
+        let (inner, width) = if i % 5 == 0 { (0.80, 0.03) } else { (0.88, 0.012) };
+        let degrees = i as f64 * 6.0;
+        Stroke { from: point(degrees, inner), to: point(degrees, FACE_RADIUS), width }
+    }).collect();
+    if let Some(minutes) = minutes {
+        let (hour, minute) = ((minutes / 60 % 12) as f64, (minutes % 60) as f64);
+        strokes.push(Stroke { from: (0.0, 0.0), to: point((hour + minute / 60.0) * 30.0, 0.45), width: 0.06 });
+        strokes.push(Stroke { from: (0.0, 0.0), to: point(minute * 6.0, 0.72), width: 0.035 });
+    }
+    strokes
+}
+
+// 5x7 点阵数字，每行的低 5 位从左到右
+const DIGITS: [[u8; 7]; 10] = [
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+];
+
+// 钟面的 png 图片，minutes 为 None 时没有指针
+pub fn clock_png(minutes: Option<u16>) -> Vec<u8> {
+    let size = CLOCK_PIXELS;
+    let scale = size as f64 / 2.0;
+    let mut ink = vec![0f64; (size * size) as usize];
+    // 按与形状的距离计算覆盖比例，边缘平滑
+    let mut draw = |bounds: ((f64, f64), (f64, f64)), half_width: f64, distance: &dyn Fn((f64, f64)) -> f64| {
+        let to_pixel = |v: f64| ((v + 1.0) * scale).clamp(0.0, size as f64) as u32;
+        let pad = half_width + 2.0 / scale;
+        for y in to_pixel(bounds.0.1 - pad)..to_pixel(bounds.1.1 + pad) {
+            for x in to_pixel(bounds.0.0 - pad)..to_pixel(bounds.1.0 + pad) {
+                let p = ((x as f64 + 0.5) / scale - 1.0, (y as f64 + 0.5) / scale - 1.0);
+                let coverage = ((half_width - distance(p)) * scale + 0.5).clamp(0.0, 1.0);
+                let i = (y * size + x) as usize;
+                ink[i] = ink[i].max(coverage);
+            }
+        }
+    };
+
+    let face = FACE_RADIUS + FACE_WIDTH;
+    draw(((-face, -face), (face, face)), FACE_WIDTH / 2.0, &|(x, y)| ((x * x + y * y).sqrt() - FACE_RADIUS).abs());
+    for stroke in strokes(minutes) {
+        let bounds = ((stroke.from.0.min(stroke.to.0), stroke.from.1.min(stroke.to.1)), (stroke.from.0.max(stroke.to.0), stroke.from.1.max(stroke.to.1)));
+        draw(bounds, stroke.width / 2.0, &|p| segment_distance(p, stroke.from, stroke.to));
+    }
+    if minutes.is_some() {
+        draw(((-CENTER_RADIUS, -CENTER_RADIUS), (CENTER_RADIUS, CENTER_RADIUS)), CENTER_RADIUS, &|(x, y)| (x * x + y * y).sqrt());
+    }
+    // 数字的每个点是一个小方块
+    let dot = NUMBER_HEIGHT / 7.0;
+    for hour in 1..=12u32 {
+        let text = hour.to_string();
+        let (cx, cy) = point(hour as f64 * 30.0, NUMBER_RADIUS);
+        let width = text.len() as f64 * 6.0 * dot - dot;
+        for (n, digit) in text.bytes().enumerate() {
+            for (row, bits) in DIGITS[(digit - b'0') as usize].iter().enumerate() {
+                for column in 0..5 {
+                    if bits & (0b10000 >> column) != 0 {
+                        let x = cx - width / 2.0 + (n as f64 * 6.0 + column as f64 + 0.5) * dot;
+                        let y = cy - NUMBER_HEIGHT / 2.0 + (row as f64 + 0.5) * dot;
+                        // 相邻的点略有重叠，数字的笔画连续
+                        draw(((x, y), (x, y)), dot * 0.6, &|p| (p.0 - x).abs().max((p.1 - y).abs()));
+                    }
+                }
+            }
+        }
+    }
+
+    let image = GrayImage::from_fn(size, size, |x, y| Luma([(255.0 * (1.0 - ink[(y * size + x) as usize])) as u8]));
+    let mut png = Cursor::new(vec![]);
+    image.write_to(&mut png, ImageFormat::Png).expect("Unable to encode clock image");
+    png.into_inner()
+}
+
+// 点到线段的距离
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 { 0.0 } else { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).clamp(0.0, 1.0) };
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+// 插入 docx 的钟面图片
+pub fn clock_run(minutes: Option<u16>) -> Run {
+    let size = CLOCK_DISPLAY_PIXELS * EMU_PER_PIXEL;
+    Run::new().add_image(Pic::new_with_dimensions(clock_png(minutes), CLOCK_PIXELS, CLOCK_PIXELS).size(size, size))
+}
+
+// 与 png 相同的钟面，用于 html
+pub fn clock_svg(minutes: Option<u16>) -> String {
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 2 2\" width=\"{0}\" height=\"{0}\" style=\"vertical-align: middle\">", CLOCK_DISPLAY_PIXELS);
+    svg.push_str(&format!("<circle r=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\"/>", FACE_RADIUS, FACE_WIDTH));
+    for stroke in strokes(minutes) {
+        svg.push_str(&format!("<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\" stroke=\"black\" stroke-width=\"{}\" stroke-linecap=\"round\"/>",
+                              stroke.from.0, stroke.from.1, stroke.to.0, stroke.to.1, stroke.width));
+    }
+    if minutes.is_some() {
+        svg.push_str(&format!("<circle r=\"{}\"/>", CENTER_RADIUS));
+    }
+    for hour in 1..=12 {
+        let (x, y) = point(hour as f64 * 30.0, NUMBER_RADIUS);
+        svg.push_str(&format!("<text x=\"{:.3}\" y=\"{:.3}\" font-size=\"{}\" font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                              x, y, NUMBER_HEIGHT * 1.3, hour));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use image::GenericImageView;
+    use crate::clock::{clock_png, clock_svg, CLOCK_PIXELS};
+
+    #[test]
+    fn test_clock_png() {
+        let blank = image::load_from_memory(&clock_png(None)).unwrap();
+        assert_eq!(blank.dimensions(), (CLOCK_PIXELS, CLOCK_PIXELS));
+        let center = CLOCK_PIXELS / 2;
+        // 空白钟面的中心没有指针，3:00 的时针指向右边，分针指向上方
+        let dark = |image: &image::DynamicImage, x: u32, y: u32| image.get_pixel(x, y).0[0] < 128;
+        assert!(!dark(&blank, center, center));
+        let three = image::load_from_memory(&clock_png(Some(15 * 60))).unwrap();
+        assert!(dark(&three, center, center));
+        assert!(dark(&three, center + CLOCK_PIXELS / 8, center));
+        assert!(dark(&three, center, center - CLOCK_PIXELS / 4));
+        assert!(!dark(&three, center - CLOCK_PIXELS / 8, center));
+    }
+
+    #[test]
+    fn test_clock_svg() {
+        let svg = clock_svg(Some(9 * 60 + 40));
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<line").count(), 62);
+        assert_eq!(clock_svg(None).matches("<line").count(), 60);
+        assert!(svg.contains(">12</text>"));
+    }
+}
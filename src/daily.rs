@@ -0,0 +1,296 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use crate::DailyOpts;
+use crate::add_minus::{fact_problem, pattern_facts};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Fact, Op};
+use crate::progress::{load_records, now, records_to_csv, student_path, Record, Store};
+use crate::quiz::{ask_all, print_summary, prompt_text};
+use crate::utils::new_rng;
+
+// Leitner 盒子，第i个盒子中的事实答对后隔 INTERVALS[i] 天再复习，答错回到第一个盒子
+const INTERVALS: [u64; 5] = [1, 2, 4, 8, 16];
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+// 一个事实的复习记录
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Card {
+    pub fact: Fact,
+    // 所在盒子，从1开始
+    #[serde(rename = "box")]
+    pub level: usize,
+    // 下次复习的日期，从 1970-01-01 开始的天数
+    pub due: u64,
+    pub reviews: u32,
+    pub lapses: u32,
+}
+
+// 一个学生的复习计划
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Schedule {
+    pub cards: Vec<Card>,
+}
+
+impl Schedule {
+    // 没有计划的学生返回空计划
+    pub fn load(path: &Path) -> Result<Schedule, Error> {
+        if !path.exists() {
+            return Ok(Schedule::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // 今天的练习：先安排到期的事实（最早到期、盒子最小的优先），不足 count 时按顺序补充新的事实
+    pub fn plan(&self, facts: &[Fact], count: usize, today: u64) -> (Vec<Fact>, Vec<Fact>) {
+        let mut due: Vec<&Card> = self.cards.iter().filter(|c| c.due <= today).collect();
+        due.sort_by_key(|c| (c.due, c.level));
+        let due: Vec<Fact> = due.iter().take(count).map(|c| c.fact).collect();
+
+        let fresh = facts.iter()
+            .filter(|f| !self.cards.iter().any(|c| c.fact == **f))
+            .take(count - due.len())
+            .copied()
+            .collect();
+        (due, fresh)
+    }
+
+    // 记录一次作答，返回是否属于计划中的事实
+    pub fn review(&mut self, fact: &Fact, correct: bool, today: u64) -> bool {
+        let fact = canonical(fact);
+        if !pattern_facts().contains(&fact) {
+            return false;
+        }
+        let index = match self.cards.iter().position(|c| c.fact == fact) {
+            Some(i) => i,
+            None => {
+                self.cards.push(Card { fact, level: 1, due: today, reviews: 0, lapses: 0 });
+                self.cards.len() - 1
+            }
+        };
+        let card = &mut self.cards[index];
+        card.reviews += 1;
+        if correct {
+            card.level = (card.level + 1).min(INTERVALS.len());
+        } else {
+            card.level = 1;
+            card.lapses += 1;
+        }
+        card.due = today + INTERVALS[card.level - 1];
+        true
+    }
+
+    // 下一次有事实到期的日期
+    pub fn next_due(&self) -> Option<u64> {
+        self.cards.iter().map(|c| c.due).min()
+    }
+}
+
+// 每个事实一行，correct 为 true，格式与 progress export --raw 相同，可以直接用于 --record
+fn record_template(facts: &[Fact]) -> Vec<Record> {
+    facts.iter().map(|fact| {
+        let problem = fact_problem(fact);
+        Record {
+            timestamp: 0,
+            question: prompt_text(&problem),
+            answers: problem.answers.clone(),
+            given: vec![],
+            correct: true,
+            elapsed_ms: 0,
+            fact: Some(*fact),
+        }
+    }).collect()
+}
+
+// 加法交换律：9 + 7 与 7 + 9 是同一个事实，统一为大数在前，与 p1 的写法一致
+fn canonical(fact: &Fact) -> Fact {
+    match fact.op {
+        Op::Add if fact.left < fact.right => Fact { left: fact.right, op: fact.op, right: fact.left },
+        _ => *fact,
+    }
+}
+
+pub fn today(utc_offset: i64) -> u64 {
+    day_of(now(), utc_offset)
+}
+
+// unix 时间戳在相对 UTC 的 utc_offset 小时的时区中是哪一天
+fn day_of(timestamp: u64, utc_offset: i64) -> u64 {
+    (timestamp as i64 + utc_offset * 60 * 60).div_euclid(SECS_PER_DAY as i64) as u64
+}
+
+// 从 1970-01-01 开始的天数转为 yyyy-mm-dd
+pub fn format_day(day: u64) -> String {
+    let z = day as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+impl DailyOpts {
+    pub fn run(&self) -> Result<(), Error> {
+        let today = today(self.utc_offset);
+        let path = self.schedule_path();
+        let mut schedule = Schedule::load(&path)?;
+
+        if let Some(results) = &self.record {
+            let records = load_records(results)?;
+            self.record_results(&mut schedule, &records, today);
+            return schedule.save(&path);
+        }
+
+        let (due, fresh) = schedule.plan(&pattern_facts(), self.count as usize, today);
+        println!("{}", tf("daily.plan", &[("student", &self.student), ("day", &format_day(today)), ("due", &due.len()), ("new", &fresh.len())]));
+        let facts: Vec<Fact> = due.into_iter().chain(fresh).collect();
+        if facts.is_empty() {
+            println!("{}", t("daily.nothing"));
+            return Ok(());
+        }
+
+        if self.quiz {
+            let mut rng = new_rng(self.sheet.seed);
+            let problems = self.gen_section(&facts, &mut rng).problems;
+            let stdin = io::stdin();
+            let attempts = ask_all(&problems, &mut stdin.lock(), &mut io::stdout())?;
+            print_summary(&attempts, problems.len(), &mut io::stdout())?;
+
+            let timestamp = now();
+            let records: Vec<Record> = attempts.iter().map(|a| Record::from_attempt(a, timestamp)).collect();
+            Store::new(&self.store.data_dir).append(&self.student, &records)?;
+            self.record_results(&mut schedule, &records, today);
+            schedule.save(&path)
+        } else {
+            let title = tf("doc.daily_title", &[("date", &format_day(today))]);
+            let name = format!("daily-{}-{}", self.student, format_day(today));
+            let output = student_path(Path::new("./output"), &name, "docx");
+            self.sheet.write(&self.layout, Some(&title), &output.to_string_lossy(), Source::new("daily", self), |rng| Ok(vec![self.gen_section(&facts, rng)]))?;
+            // 登记结果用的 csv，默认全部答对
+            let csv = student_path(Path::new("./output"), &name, "csv");
+            fs::write(&csv, records_to_csv(&record_template(&facts)))?;
+            println!("{}", tf("daily.record_hint", &[("csv", &csv.display()), ("student", &self.student)]));
+            Ok(())
+        }
+    }
+
+    fn schedule_path(&self) -> PathBuf {
+        student_path(&self.store.data_dir.join("schedule"), &self.student, "json")
+    }
+
+    fn gen_section(&self, facts: &[Fact], rng: &mut impl Rng) -> Section {
+        let mut problems: Vec<_> = facts.iter().map(fact_problem).collect();
+        problems.shuffle(rng);
+        Section {
+            heading: None,
+            problems,
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: false,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    fn record_results(&self, schedule: &mut Schedule, records: &[Record], today: u64) {
+        let facts: Vec<(Fact, bool)> = records.iter().filter_map(|r| r.fact.map(|f| (f, r.correct))).collect();
+        let reviewed = facts.iter().filter(|(f, correct)| schedule.review(f, *correct, today)).count();
+        println!("{}", tf("daily.updated", &[("reviewed", &reviewed), ("ignored", &(records.len() - reviewed))]));
+        if let Some(day) = schedule.next_due() {
+            println!("{}", tf("daily.next_review", &[("day", &format_day(day))]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::add_minus::pattern_facts;
+    use crate::daily::{day_of, format_day, record_template, Schedule};
+    use crate::problem::{Fact, Op};
+    use crate::progress::{load_records, records_to_csv};
+
+    fn add(left: i64, right: i64) -> Fact {
+        Fact { left, op: Op::Add, right }
+    }
+
+    #[test]
+    fn test_format_day() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(11016), "2000-02-29");
+        assert_eq!(format_day(20745), "2026-10-19");
+    }
+
+    #[test]
+    fn test_day_of() {
+        // UTC 16:00 在北京已经是第二天
+        assert_eq!(day_of(16 * 3600 - 1, 8), 0);
+        assert_eq!(day_of(16 * 3600, 8), 1);
+        assert_eq!(day_of(16 * 3600, 0), 0);
+        assert_eq!(day_of(86400 + 3600, -5), 0);
+    }
+
+    #[test]
+    fn test_record_template() {
+        let facts = [add(9, 7), Fact { left: 13, op: Op::Minus, right: 5 }];
+        let path = std::env::temp_dir().join(format!("gen_arithmatic_daily_{}.csv", std::process::id()));
+        std::fs::write(&path, records_to_csv(&record_template(&facts))).unwrap();
+        let records = load_records(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(records.iter().map(|r| r.fact.unwrap()).collect::<Vec<_>>(), facts);
+        assert!(records.iter().all(|r| r.correct));
+    }
+
+    #[test]
+    fn test_review() {
+        let mut schedule = Schedule::default();
+        assert!(schedule.review(&add(7, 9), true, 100));
+        // 7 + 9 与 9 + 7 是同一个事实
+        assert_eq!(schedule.cards[0].fact, add(9, 7));
+        assert_eq!((schedule.cards[0].level, schedule.cards[0].due), (2, 102));
+        assert!(schedule.review(&add(9, 7), true, 102));
+        assert_eq!((schedule.cards[0].level, schedule.cards[0].due), (3, 106));
+        assert!(schedule.review(&add(9, 7), false, 106));
+        assert_eq!((schedule.cards[0].level, schedule.cards[0].due, schedule.cards[0].lapses), (1, 107, 1));
+        // 不在 p1~p4 中的事实不安排复习
+        assert!(!schedule.review(&add(23, 15), true, 100));
+        assert_eq!(schedule.cards.len(), 1);
+    }
+
+    #[test]
+    fn test_plan() {
+        let facts = pattern_facts();
+        let mut schedule = Schedule::default();
+        let (due, fresh) = schedule.plan(&facts, 5, 100);
+        assert!(due.is_empty());
+        assert_eq!(fresh, facts[..5]);
+
+        schedule.review(&facts[0], false, 100);
+        schedule.review(&facts[1], true, 100);
+        schedule.review(&facts[2], true, 100);
+        schedule.review(&facts[2], true, 102);
+        // 第二天：只有答错的到期
+        let (due, fresh) = schedule.plan(&facts, 5, 101);
+        assert_eq!(due, vec![facts[0]]);
+        assert_eq!(fresh, facts[3..7]);
+        // 第三天：答对一次的也到期，最早到期的在前
+        let (due, _) = schedule.plan(&facts, 5, 102);
+        assert_eq!(due, vec![facts[0], facts[1]]);
+    }
+}
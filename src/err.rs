@@ -1,12 +1,28 @@
-use std::io;
+use std::{fmt, io};
+use crate::i18n::t;
 
-// 定制错误
+// 定制错误，Program 中为消息的键，显示时翻译为当前语言
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
     Program(&'static str),
+    // 解析用户输入（比如描述文件）出错，包含具体的出错信息
+    Parse(String),
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Program(key) => write!(f, "{}", t(key)),
+            Error::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         Error::Io(e)
@@ -17,6 +33,21 @@ impl From<&'static str> for Error {
         Error::Program(e)
     }
 }
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+impl From<clap::Error> for Error {
+    fn from(e: clap::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
 
 #[cfg(test)]
 mod test{
@@ -27,27 +58,22 @@ mod test{
     fn test_str_convert() {
         let e: Error = "error in program".into();
         let e2 = <&str>::into("another error in program");
-        match e {
-            Error::Program(d) => assert_eq!(d, "error in program"),
-            _ => ()
+        if let Error::Program(d) = e {
+            assert_eq!(d, "error in program");
         }
-        match e2 {
-            Error::Program(d) => assert_eq!(d, "another error in program"),
-            _ => ()
+        if let Error::Program(d) = e2 {
+            assert_eq!(d, "another error in program");
         }
     }
 
     #[test]
     fn test_io_error_convert() {
         let file = File::open("non exist");
-        assert!(!file.is_ok());
-        match file {
-            Err(e) => {
-                let custom_error: Error = e.into();
-                let msg = format!("{:?}", custom_error);
-                assert!(msg.eq("Io(Os { code: 2, kind: NotFound, message: \"No such file or directory\" })"));
-            },
-            _ => (),
+        assert!(file.is_err());
+        if let Err(e) = file {
+            let custom_error: Error = e.into();
+            let msg = format!("{:?}", custom_error);
+            assert!(msg.eq("Io(Os { code: 2, kind: NotFound, message: \"No such file or directory\" })"));
         }
     }
 }
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::{Serialize, Serializer};
+use crate::FractionsOpts;
+use crate::add_minus::MAX_ATTEMPTS;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// --denominators 的上限
+pub const MAX_DENOMINATOR: u64 = 1000;
+
+// 最简分数，分母总是正数，整数的分母为 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction {
+    pub num: i64,
+    pub den: i64,
+}
+
+pub fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i64
+}
+
+impl Fraction {
+    // 约分为最简分数，分母为 0 时为 None
+    pub fn new(num: i64, den: i64) -> Option<Fraction> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num, den).max(1);
+        let sign = if den < 0 { -1 } else { 1 };
+        Some(Fraction { num: sign * num / g, den: sign * den / g })
+    }
+
+    pub fn checked_add(self, other: Fraction) -> Option<Fraction> {
+        let num = self.num.checked_mul(other.den)?.checked_add(other.num.checked_mul(self.den)?)?;
+        Fraction::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    pub fn checked_sub(self, other: Fraction) -> Option<Fraction> {
+        self.checked_add(Fraction { num: other.num.checked_neg()?, den: other.den })
+    }
+
+    // 带分数的写法：7/6 -> 1 1/6，2/1 -> 2，1/2 -> 1/2
+    pub fn mixed_text(&self) -> String {
+        let (whole, rest) = (self.num / self.den, (self.num % self.den).abs());
+        match (whole, rest) {
+            (_, 0) => whole.to_string(),
+            (0, _) => self.to_string(),
+            _ => format!("{} {}/{}", whole, rest, self.den),
+        }
+    }
+}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.num as i128 * other.den as i128).cmp(&(other.num as i128 * self.den as i128))
+    }
+}
+
+// 假分数的写法，比如 7/6，整数不写分母
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+// 命令行格式：2, 3/4, 7/6 或带分数 "1 1/6"
+impl FromStr for Fraction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || tf("error.fraction", &[("value", &s)]);
+        let parse = |n: &str| n.trim().parse::<i64>().map_err(|_| invalid());
+        let (whole, fraction) = match s.trim().split_once(' ') {
+            Some((whole, fraction)) => (Some(parse(whole)?), fraction),
+            None => (None, s.trim()),
+        };
+        let fraction = match fraction.split_once('/') {
+            Some((num, den)) => Fraction::new(parse(num)?, parse(den)?).ok_or_else(invalid)?,
+            None if whole.is_none() => Fraction { num: parse(fraction)?, den: 1 },
+            None => return Err(invalid()),
+        };
+        match whole {
+            // 带分数的分数部分是正的真分数，负号写在整数部分
+            Some(whole) if fraction.num > 0 && fraction.num < fraction.den => {
+                let whole = Fraction { num: whole, den: 1 };
+                if whole.num < 0 { whole.checked_sub(fraction) } else { whole.checked_add(fraction) }.ok_or_else(invalid)
+            }
+            Some(_) => Err(invalid()),
+            None => Ok(fraction),
+        }
+    }
+}
+
+// 与命令行格式相同
+impl Serialize for Fraction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FractionsOpts {
+    pub fn gen_fractions_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/fractions.docx", Source::new("fractions", self), |rng| Ok(vec![self.gen_section(None, rng)?]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Result<Section, Error> {
+        Ok(Section {
+            heading,
+            problems: self.gen_problems(rng)?,
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            // 上下排列的分数比文字高，每行之后空一行
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        })
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Result<Vec<Problem>, Error> {
+        (0..self.count).map(|_| {
+            let op = if self.category.starts_with('+') {
+                Op::Add
+            } else if self.category.starts_with('_') {
+                Op::Minus
+            } else if rng.random() {
+                Op::Add
+            } else {
+                Op::Minus
+            };
+            self.gen_problem(op, rng)
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.fractions")
+    }
+
+    // 结果在 [result_min_inclusive, result_max_inclusive] 中，减法的结果不小于 0
+    // 尝试 MAX_ATTEMPTS 次都不满足时认为参数互相矛盾，返回错误
+    fn gen_problem(&self, op: Op, rng: &mut impl Rng) -> Result<Problem, Error> {
+        let min = match op {
+            Op::Minus => self.result_min_inclusive.max(Fraction { num: 0, den: 1 }),
+            _ => self.result_min_inclusive,
+        };
+        for _ in 0..MAX_ATTEMPTS {
+            let left_den = *self.denominators.choose(rng).unwrap();
+            let right_den = if self.same_denominator { left_den } else { *self.denominators.choose(rng).unwrap() };
+            let (mut l, mut r) = (self.gen_operand(left_den as i64, rng), self.gen_operand(right_den as i64, rng));
+            if op == Op::Minus && l < r {
+                (l, r) = (r, l);
+            }
+            let ans = match op {
+                Op::Minus => l.checked_sub(r),
+                _ => l.checked_add(r),
+            };
+            match ans {
+                Some(ans) if min <= ans && ans <= self.result_max_inclusive => {
+                    let segments = vec![
+                        Segment::Fraction(l.mixed_text()),
+                        Segment::Text(format!(" {} ", op.symbol())),
+                        Segment::Fraction(r.mixed_text()),
+                        Segment::Text(" = ".to_string()),
+                        Segment::Blank(0),
+                    ];
+                    return Ok(Problem { segments, answers: vec![ans.mixed_text()], fact: None });
+                }
+                _ => continue,
+            }
+        }
+        Err(Error::Parse(tf("error.no_fractions", &[("min", &min), ("max", &self.result_max_inclusive)])))
+    }
+
+    // 分子与分母互质的真分数，--mixed 时加上整数部分成为带分数
+    fn gen_operand(&self, den: i64, rng: &mut impl Rng) -> Fraction {
+        // 1 总是与分母互质，随机选取直到互质为止
+        let num = loop {
+            let num = rng.random_range(1..den);
+            if gcd(num, den) == 1 {
+                break num;
+            }
+        };
+        let whole = if self.mixed { rng.random_range(0..=self.max_whole as i64) } else { 0 };
+        Fraction { num: whole * den + num, den }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use crate::fraction::Fraction;
+    use crate::{Cli, FractionsOpts};
+    use crate::utils::new_rng;
+
+    fn f(s: &str) -> Fraction {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_fraction() {
+        assert_eq!(Fraction::new(4, 6), Some(Fraction { num: 2, den: 3 }));
+        assert_eq!(Fraction::new(3, -9), Some(Fraction { num: -1, den: 3 }));
+        assert_eq!(Fraction::new(1, 0), None);
+        assert_eq!(f("2/3").checked_add(f("1/6")), Some(f("5/6")));
+        assert_eq!(f("1/2").checked_sub(f("3/4")), Some(f("-1/4")));
+        assert_eq!(f("1 1/2"), f("3/2"));
+        assert_eq!(f("-1 1/2"), f("-3/2"));
+        assert!("1 3/2".parse::<Fraction>().is_err());
+        assert!("1/0".parse::<Fraction>().is_err());
+        assert!("a/2".parse::<Fraction>().is_err());
+        assert!(f("2/3") > f("3/5"));
+
+        assert_eq!(f("7/6").mixed_text(), "1 1/6");
+        assert_eq!(f("8/4").mixed_text(), "2");
+        assert_eq!(f("1/2").mixed_text(), "1/2");
+        assert_eq!(f("-7/6").mixed_text(), "-1 1/6");
+        assert_eq!(f("7/6").to_string(), "7/6");
+    }
+
+    // 题目中的两个运算数和运算符
+    fn operands(problem: &crate::problem::Problem) -> (Fraction, String, Fraction) {
+        let text = problem.solved_text();
+        let (question, _) = text.split_once(" = ").unwrap();
+        let (l, rest) = question.split_once(['+', '-']).unwrap();
+        let op = question[l.len()..l.len() + 1].to_string();
+        (f(l.trim()), op, f(rest.trim()))
+    }
+
+    #[test]
+    fn test_gen_problems() {
+        let opts = FractionsOpts { count: 40, category: "x".to_string(), denominators: vec![2, 3, 4, 6], result_max_inclusive: f("10"), ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(1))).unwrap() {
+            let (l, op, r) = operands(&problem);
+            let ans = if op == "+" { l.checked_add(r) } else { l.checked_sub(r) }.unwrap();
+            // 答案是最简的带分数，减法不为负
+            assert_eq!(problem.answers, vec![ans.mixed_text()]);
+            assert!(ans >= f("0"));
+            assert!([2, 3, 4, 6].contains(&l.den) && [2, 3, 4, 6].contains(&r.den));
+            assert!(l < f("1") && r < f("1"));
+        }
+
+        let opts = FractionsOpts { same_denominator: true, mixed: true, max_whole: 3, category: "_".to_string(), result_min_inclusive: f("1/2"), result_max_inclusive: f("2"), ..opts };
+        let problems = opts.gen_problems(&mut new_rng(Some(2))).unwrap();
+        for problem in &problems {
+            let (l, op, r) = operands(problem);
+            assert_eq!((op.as_str(), l.den), ("-", r.den));
+            let ans = l.checked_sub(r).unwrap();
+            assert!(f("1/2") <= ans && ans <= f("2"));
+        }
+        // 有带分数的运算数
+        assert!(problems.iter().any(|p| operands(p).0 > f("1")));
+        assert_eq!(problems, opts.gen_problems(&mut new_rng(Some(2))).unwrap());
+
+        // 真分数相加的结果小于 2，-b 5 -e 6 无法满足
+        let opts = FractionsOpts { mixed: false, result_min_inclusive: f("5"), result_max_inclusive: f("6"), ..opts };
+        assert!(opts.gen_problems(&mut new_rng(Some(3))).is_err());
+
+        // 分母很大时也能很快选出分子
+        let opts = FractionsOpts { denominators: vec![1000], result_min_inclusive: f("0"), result_max_inclusive: f("2"), ..opts };
+        assert!(opts.gen_problems(&mut new_rng(Some(4))).unwrap().iter().all(|p| operands(p).0.den <= 1000));
+        assert!(Cli::try_parse_from(["gen_arithmatic", "fractions", "-d", "18446744073709551615"]).is_err());
+    }
+}
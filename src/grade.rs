@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+use docx_rs::{AlignmentType, Docx, Paragraph, Run, RunFonts};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::i18n::{lang, t, tf};
+use crate::{GradeOpts, OutputFormat};
+use crate::err::Error;
+use crate::manifest::Manifest;
+use crate::problem::Problem;
+use crate::progress::split_csv_line;
+use crate::quiz::split_answers;
+use crate::utils::{escape_html, new_paragraph, read_from_docx, write, write_to_docx};
+
+// 批改结果的字体大小
+const GRADE_FONT_SIZE: usize = 28;
+const WRONG_COLOR: &str = "FF0000";
+
+// 一道题的批改结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub problem: Problem,
+    pub given: Vec<String>,
+    pub correct: bool,
+}
+
+impl GradeOpts {
+    pub fn grade_to_file(&self) -> Result<(), Error> {
+        let problems = self.regenerate()?;
+        let content = fs::read_to_string(&self.answers)?;
+        let answers = parse_answers(&content, is_csv(&self.answers));
+        if answers.len() > problems.len() {
+            eprintln!("{}", tf("grade.extra_answers", &[("count", &(answers.len() - problems.len()))]));
+        }
+
+        let marks = grade(&problems, answers);
+        let correct = marks.iter().filter(|m| m.correct).count();
+        println!("{}", tf("grade.total", &[("correct", &correct), ("total", &marks.len())]));
+
+        let output = self.output.clone().unwrap_or_else(|| match self.format {
+            OutputFormat::Docx => "./output/graded.docx".to_string(),
+            OutputFormat::Html => "./output/graded.html".to_string(),
+        });
+        match self.format {
+            OutputFormat::Docx => write_to_docx(to_docx(read_from_docx("./resources/template.docx"), &marks), &output),
+            OutputFormat::Html => {
+                write(&to_html(&marks), &output)?;
+                println!("{}", t("doc.generated_html"));
+            }
+        }
+        Ok(())
+    }
+
+    // 从清单中读取题目，或者按种子重新生成与试卷完全相同的题目，第n个版本的种子为 seed + n - 1
+    fn regenerate(&self) -> Result<Vec<Problem>, Error> {
+        let version = self.version.max(1) as usize - 1;
+        if let Some(path) = &self.manifest {
+            return Manifest::load(path)?.problems(version).ok_or_else(|| Error::Parse(tf("error.no_version", &[("version", &self.version)])));
+        }
+        let generator = self.generator.as_ref().ok_or("error.grade_needs_input")?;
+        let seed = generator.sheet().seed.ok_or("error.grade_needs_seed")?;
+        Ok(generator.gen_section(None, &mut StdRng::seed_from_u64(seed.wrapping_add(version as u64)))?.problems)
+    }
+}
+
+// 每道题一份答案，一道题有多个空时用空格分隔：
+// csv: 按顺序每个单元格一道题，可以有多行，空单元格表示未作答
+// 其他: 每行一道题，空行表示未作答
+pub fn parse_answers(content: &str, csv: bool) -> Vec<Vec<String>> {
+    let mut answers: Vec<Vec<String>> = if csv {
+        content.lines()
+            .filter(|l| !l.trim().is_empty())
+            .flat_map(split_csv_line)
+            .map(|cell| split_answers(&cell))
+            .collect()
+    } else {
+        content.lines().map(split_answers).collect()
+    };
+    // 末尾的空答案没有意义
+    while answers.last().is_some_and(|a| a.is_empty()) {
+        answers.pop();
+    }
+    answers
+}
+
+// 没有答案的题目算作答错；画指针这样没有标准答案的题目仍占一行答案，但不批改也不计分
+pub fn grade(problems: &[Problem], answers: Vec<Vec<String>>) -> Vec<Mark> {
+    let mut answers = answers.into_iter();
+    problems.iter().filter_map(|problem| {
+        let given = answers.next().unwrap_or_default();
+        if problem.answers.is_empty() {
+            return None;
+        }
+        let correct = problem.is_correct(&given);
+        Some(Mark { problem: problem.clone(), given, correct })
+    }).collect()
+}
+
+fn score_text(marks: &[Mark]) -> String {
+    let correct = marks.iter().filter(|m| m.correct).count();
+    let percent = if marks.is_empty() { 0.0 } else { correct as f64 * 100.0 / marks.len() as f64 };
+    tf("grade.score", &[("correct", &correct), ("total", &marks.len()), ("percent", &format!("{:.0}", percent))])
+}
+
+fn mark_text(n: usize, mark: &Mark) -> String {
+    let text = mark.problem.filled_text(&mark.given);
+    if mark.correct {
+        format!("{}. {}  ✓", n, text.trim())
+    } else {
+        format!("{}. {}  ✗  {}", n, text.trim(), tf("grade.correct_answer", &[("answer", &mark.problem.answers.join(" "))]))
+    }
+}
+
+pub fn to_docx(mut doc: Docx, marks: &[Mark]) -> Docx {
+    doc = doc.add_paragraph(new_paragraph(GRADE_FONT_SIZE, &t("grade.title")).bold().align(AlignmentType::Center))
+        .add_paragraph(new_paragraph(GRADE_FONT_SIZE, &score_text(marks)).bold());
+    for (i, mark) in marks.iter().enumerate() {
+        let mut run = Run::new().size(GRADE_FONT_SIZE).fonts(RunFonts::new().ascii("Courier New")).add_text(mark_text(i + 1, mark));
+        if !mark.correct {
+            run = run.color(WRONG_COLOR);
+        }
+        doc = doc.add_paragraph(Paragraph::new().size(GRADE_FONT_SIZE).add_run(run));
+    }
+    doc
+}
+
+pub fn to_html(marks: &[Mark]) -> String {
+    let title = escape_html(&t("grade.title"));
+    let mut html = format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n", lang(), title);
+    html.push_str("body { font-family: \"Courier New\", monospace; }\n.wrong { color: #FF0000; }\n");
+    html.push_str(&format!("</style>\n</head>\n<body>\n<h1>{}</h1>\n", title));
+    html.push_str(&format!("<p><b>{}</b></p>\n<ol>\n", escape_html(&score_text(marks))));
+    for (i, mark) in marks.iter().enumerate() {
+        let class = if mark.correct { "right" } else { "wrong" };
+        let text = mark_text(i + 1, mark);
+        // 序号由 <ol> 给出
+        let text = text.split_once(". ").map(|(_, t)| t).unwrap_or(&text);
+        html.push_str(&format!("<li class=\"{}\">{}</li>\n", class, escape_html(text)));
+    }
+    html.push_str("</ol>\n</body>\n</html>\n");
+    html
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::grade::{grade, parse_answers, to_html};
+    use crate::problem::{Problem, Segment};
+
+    fn problems() -> Vec<Problem> {
+        vec![
+            Problem::expr("3 + 4 =".to_string(), "7".to_string()),
+            Problem::expr("9 - 5 =".to_string(), "4".to_string()),
+            Problem::joined(vec![Segment::Text("1".to_string()), Segment::Blank(1), Segment::Blank(1), Segment::Text("4".to_string())],
+                            vec!["2".to_string(), "3".to_string()]),
+        ]
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        assert_eq!(parse_answers("7\n\n2 3\n\n", false), vec![vec!["7"], vec![], vec!["2", "3"]]);
+        assert_eq!(parse_answers("7,,2 3\n", true), vec![vec!["7"], vec![], vec!["2", "3"]]);
+        assert_eq!(parse_answers("7,5\n\"2,3\"\n", true), vec![vec!["7"], vec!["5"], vec!["2", "3"]]);
+    }
+
+    #[test]
+    fn test_grade() {
+        let marks = grade(&problems(), parse_answers("7,5", true));
+        assert_eq!(marks.iter().map(|m| m.correct).collect::<Vec<_>>(), vec![true, false, false]);
+        assert!(marks[2].given.is_empty());
+
+        let html = to_html(&marks);
+        assert!(html.contains("得分：1/3 (33%)"));
+        assert!(html.contains("<li class=\"right\">3 + 4 = 7  ✓</li>"));
+        assert!(html.contains("<li class=\"wrong\">9 - 5 = 5  ✗  正确答案：4</li>"));
+        assert!(html.contains("<li class=\"wrong\">1   4  ✗  正确答案：2 3</li>"));
+
+        // 画指针的题目不计分，但占一行答案
+        let mut problems = problems();
+        problems.insert(0, Problem { segments: vec![Segment::Clock { minutes: 0, hands: false }], answers: vec![], fact: None });
+        let marks = grade(&problems, parse_answers("\n7", false));
+        assert_eq!(marks.iter().map(|m| m.correct).collect::<Vec<_>>(), vec![true, false, false]);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::sync::OnceLock;
+use clap::Command;
+
+// 内置的语言，其他语言可以放在 ./resources/i18n/<lang>.toml
+const ZH: &str = include_str!("../resources/i18n/zh.toml");
+const EN: &str = include_str!("../resources/i18n/en.toml");
+pub const DEFAULT_LANG: &str = "zh";
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+// 一种语言的消息，找不到时依次查找后备语言
+pub struct Catalog {
+    pub lang: String,
+    messages: Vec<HashMap<String, String>>,
+}
+
+impl Catalog {
+    pub fn new(lang: &str) -> Catalog {
+        let mut messages = vec![];
+        match lang {
+            "zh" => messages.push(parse(ZH)),
+            "en" => messages.push(parse(EN)),
+            _ => {
+                // 外部的消息文件，缺少的消息用英文
+                match fs::read_to_string(format!("./resources/i18n/{}.toml", lang)) {
+                    Ok(content) => messages.push(parse(&content)),
+                    Err(_) => eprintln!("No message catalog for '{}', using English", lang),
+                }
+                messages.push(parse(EN));
+            }
+        }
+        messages.push(parse(ZH));
+        Catalog { lang: lang.to_string(), messages }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.iter().find_map(|m| m.get(key)).map(|s| s.as_str())
+    }
+}
+
+// 按 TOML 的层级展开为 a.b.c 形式的键
+fn parse(content: &str) -> HashMap<String, String> {
+    let mut messages = HashMap::new();
+    match content.parse::<toml::Table>() {
+        Ok(table) => flatten("", &toml::Value::Table(table), &mut messages),
+        Err(e) => eprintln!("Invalid message catalog: {}", e),
+    }
+    messages
+}
+
+fn flatten(prefix: &str, value: &toml::Value, messages: &mut HashMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (k, v) in table {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten(&key, v, messages);
+            }
+        }
+        toml::Value::String(s) => {
+            messages.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            messages.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+// 只能初始化一次，之后的调用不生效；未初始化时使用默认语言
+pub fn init(lang: &str) {
+    let _ = CATALOG.set(Catalog::new(lang));
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new(DEFAULT_LANG))
+}
+
+pub fn lang() -> &'static str {
+    &catalog().lang
+}
+
+// 按 --lang, LC_ALL, LC_MESSAGES, LANG 的顺序确定语言，比如 en_US.UTF-8 -> en
+pub fn detect(arg: Option<String>) -> String {
+    let env = ["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|name| std::env::var(name).ok());
+    arg.into_iter().chain(env)
+        .map(|value| value.split(['_', '.', '-', '@']).next().unwrap_or("").to_lowercase())
+        .find(|lang| !lang.is_empty() && lang != "c" && lang != "posix")
+        .unwrap_or_else(|| DEFAULT_LANG.to_string())
+}
+
+// 在解析命令行之前找出 --lang，用于决定帮助信息的语言
+pub fn lang_arg(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--lang" {
+            return iter.next().cloned();
+        }
+        if let Some(lang) = arg.strip_prefix("--lang=") {
+            return Some(lang.to_string());
+        }
+    }
+    None
+}
+
+// 找不到时返回键本身
+pub fn t(key: &str) -> String {
+    catalog().get(key).unwrap_or(key).to_string()
+}
+
+// 消息中的 {name} 替换为对应的参数
+pub fn tf(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = t(key);
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    message
+}
+
+// 命令及参数的帮助信息：cli.about.<命令>，cli.help.<命令>.<参数> 或者各命令共用的 cli.help.<参数>
+pub fn localize_command(command: Command) -> Command {
+    let catalog = catalog();
+    let name = command.get_name().to_string();
+    let mut command = command;
+    if let Some(about) = catalog.get(&format!("cli.about.{}", name)) {
+        command = command.about(about.to_string());
+    }
+    let ids: Vec<String> = command.get_arguments().map(|a| a.get_id().to_string()).collect();
+    for id in ids {
+        let help = catalog.get(&format!("cli.help.{}.{}", name, id)).or_else(|| catalog.get(&format!("cli.help.{}", id)));
+        if let Some(help) = help {
+            command = command.mut_arg(id, |a| a.help(help.to_string()));
+        }
+    }
+    let subcommands: Vec<String> = command.get_subcommands().map(|c| c.get_name().to_string()).collect();
+    for sub in subcommands {
+        command = command.mut_subcommand(sub, localize_command);
+    }
+    command
+}
+
+// 检查命令及参数在内置的各语言中都有帮助信息，并且各语言的消息相同，返回缺少的键
+#[cfg(test)]
+pub fn missing_messages(command: &Command) -> Vec<String> {
+    let zh = parse(ZH);
+    let mut missing = vec![];
+    for (lang, content) in [("zh", ZH), ("en", EN)] {
+        // 只用这一种语言，不使用后备
+        let catalog = Catalog { lang: lang.to_string(), messages: vec![parse(content)] };
+        missing_help(&catalog, command, &mut missing);
+        let messages = &catalog.messages[0];
+        missing.extend(zh.keys().filter(|k| !messages.contains_key(*k)).map(|k| format!("{}: {}", lang, k)));
+        missing.extend(messages.keys().filter(|k| !zh.contains_key(*k)).map(|k| format!("zh: {}", k)));
+    }
+    missing.sort();
+    missing
+}
+
+#[cfg(test)]
+fn missing_help(catalog: &Catalog, command: &Command, missing: &mut Vec<String>) {
+    let name = command.get_name();
+    if catalog.get(&format!("cli.about.{}", name)).is_none() {
+        missing.push(format!("{}: cli.about.{}", catalog.lang, name));
+    }
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        if matches!(arg.get_action(), clap::ArgAction::Help | clap::ArgAction::Version) {
+            continue;
+        }
+        if catalog.get(&format!("cli.help.{}.{}", name, id)).is_none() && catalog.get(&format!("cli.help.{}", id)).is_none() {
+            missing.push(format!("{}: cli.help.{}.{}", catalog.lang, name, id));
+        }
+    }
+    for sub in command.get_subcommands() {
+        missing_help(catalog, sub, missing);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::i18n::{detect, lang_arg, t, tf, Catalog};
+
+    #[test]
+    fn test_catalog() {
+        let en = Catalog::new("en");
+        assert_eq!(en.get("doc.answers"), Some("Answers"));
+        assert!(en.get("no.such.key").is_none());
+        // 没有的语言使用英文
+        let fr = Catalog::new("fr");
+        assert_eq!(fr.get("doc.answers"), Some("Answers"));
+    }
+
+    #[test]
+    fn test_tf() {
+        // 测试中未初始化，使用默认的中文
+        assert_eq!(tf("doc.version_label", &[("n", &1), ("seed", &42)]), "版本 1 (seed 42)");
+        assert_eq!(tf("doc.section_heading", &[("cn", &"二"), ("n", &2), ("heading", &"加法")]), "二、加法");
+        assert_eq!(t("no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(Some("en_US.UTF-8".to_string())), "en");
+        assert_eq!(detect(Some("zh-CN".to_string())), "zh");
+        assert_eq!(lang_arg(&["x".to_string(), "--lang".to_string(), "en".to_string()]), Some("en".to_string()));
+        assert_eq!(lang_arg(&["--lang=fr".to_string()]), Some("fr".to_string()));
+        assert_eq!(lang_arg(&["quiz".to_string()]), None);
+    }
+}
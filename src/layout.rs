@@ -0,0 +1,571 @@
+use std::fmt;
+use std::str::FromStr;
+use docx_rs::{AlignmentType, BorderType, BreakType, Docx, FieldCharType, InstrText, Paragraph, Run, RunFonts, Table, TableCell, TableCellBorder, TableCellBorderPosition, TableCellBorders, TableRow, VAlignType, WidthType};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::clock::clock_run;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::{AnswerStyle, LayoutOpts, NumberStyle, SheetOpts};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use crate::manifest::{Manifest, Source};
+use crate::long_arithmetic::{written_cells, WrittenCell};
+use crate::problem::{bond_texts, grid_text, grid_texts, written_text, Problem, Segment};
+use crate::utils::{add_paragraph, new_paragraph, read_from_docx, seed_or_random, write_to_docx};
+
+// 同一行中算式之间的分隔
+pub const PROBLEM_SEPARATOR: &str = "      ";
+
+// 一组排版方式相同的题目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section {
+    // 小节标题，没有则不输出
+    pub heading: Option<String>,
+    pub problems: Vec<Problem>,
+    // 每行多少道题
+    pub columns: u16,
+    pub font_size: usize,
+    // 每行之后是否插入空行
+    pub spaced: bool,
+    pub answer_style: AnswerStyle,
+}
+
+impl NumberStyle {
+    // 第n题（从1开始）的题号
+    pub fn label(&self, n: usize) -> String {
+        match self {
+            NumberStyle::None => String::new(),
+            NumberStyle::Arabic => format!("{}.", n),
+            NumberStyle::Circled => circled(n),
+            NumberStyle::Letter => format!("({})", letters(n)),
+        }
+    }
+}
+
+// ①~㊿，超过50则退化为(n)
+fn circled(n: usize) -> String {
+    let c = match n {
+        1..=20 => char::from_u32(0x2460 + n as u32 - 1),
+        21..=35 => char::from_u32(0x3251 + n as u32 - 21),
+        36..=50 => char::from_u32(0x32B1 + n as u32 - 36),
+        _ => None,
+    };
+    match c {
+        Some(c) => c.to_string(),
+        None => format!("({})", n),
+    }
+}
+
+// 1->a, 26->z, 27->aa
+fn letters(mut n: usize) -> String {
+    let mut s = vec![];
+    while n > 0 {
+        n -= 1;
+        s.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+    }
+    s.iter().rev().collect()
+}
+
+// 命令行格式： plain | underline[:N] | box | cell
+impl FromStr for AnswerStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, width) = match s.split_once(':') {
+            Some((name, width)) => (name, Some(width)),
+            None => (s, None),
+        };
+        match (name, width) {
+            ("plain", None) => Ok(AnswerStyle::Plain),
+            ("underline", None) => Ok(AnswerStyle::Underline(4)),
+            ("underline", Some(w)) => w.parse::<u16>()
+                .map(AnswerStyle::Underline)
+                .map_err(|_| tf("error.underline_width", &[("width", &w)])),
+            ("box", None) => Ok(AnswerStyle::Box),
+            ("cell", None) => Ok(AnswerStyle::Cell),
+            _ => Err(tf("error.answer_style", &[("style", &s)])),
+        }
+    }
+}
+
+impl fmt::Display for AnswerStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnswerStyle::Plain => write!(f, "plain"),
+            AnswerStyle::Underline(n) => write!(f, "underline:{}", n),
+            AnswerStyle::Box => write!(f, "box"),
+            AnswerStyle::Cell => write!(f, "cell"),
+        }
+    }
+}
+
+// 与命令行格式相同
+impl Serialize for AnswerStyle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnswerStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+impl AnswerStyle {
+    // 文本形式的作答区域，width 是题目自身建议的宽度
+    pub fn blank(&self, width: u16) -> String {
+        match self {
+            AnswerStyle::Plain | AnswerStyle::Cell => "_".repeat(width as usize),
+            AnswerStyle::Underline(n) => "_".repeat(*n as usize),
+            AnswerStyle::Box => "□".to_string(),
+        }
+    }
+}
+
+// 一个版本的试卷
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Version {
+    // 有多个版本时的版本标识，比如 "版本 2 (seed 43)"
+    pub label: Option<String>,
+    pub seed: u64,
+    pub sections: Vec<Section>,
+}
+
+impl SheetOpts {
+    // 按 --copies 生成各个版本，第i个版本(从0开始)的种子为 seed + i
+    pub fn gen_versions<F: FnMut(&mut StdRng) -> Result<Vec<Section>, Error>>(&self, mut gen: F) -> Result<Vec<Version>, Error> {
+        let base_seed = seed_or_random(self.seed);
+        (0..self.copies.max(1) as u64).map(|i| {
+            let seed = base_seed.wrapping_add(i);
+            let label = (self.copies > 1).then(|| tf("doc.version_label", &[("n", &(i + 1)), ("seed", &seed)]));
+            Ok(Version { label, seed, sections: gen(&mut StdRng::seed_from_u64(seed))? })
+        }).collect()
+    }
+
+    // 生成各个版本的试卷，如有需要同时生成答案页 xxx-answers.docx，并在旁边保存清单 xxx.json
+    // title 不为空时，在试卷开头写入标题以及姓名、日期、得分栏
+    pub fn write<F: FnMut(&mut StdRng) -> Result<Vec<Section>, Error>>(&self, layout: &LayoutOpts, title: Option<&str>, filepath: &str, source: Source, gen: F) -> Result<(), Error> {
+        let manifest = Manifest::new(source, layout, self, title, self.gen_versions(gen)?);
+        write_docx(&manifest.versions, self, layout, title, filepath);
+        manifest.save(filepath)
+    }
+}
+
+// 按 sheet 的 --single-file 和 --combined-key 写入试卷和答案页
+pub fn write_docx(versions: &[Version], sheet: &SheetOpts, layout: &LayoutOpts, title: Option<&str>, filepath: &str) {
+    let template = || read_from_docx("./resources/template.docx");
+
+    if sheet.single_file || versions.len() == 1 {
+        let mut doc = template();
+        for (i, version) in versions.iter().enumerate() {
+            if i > 0 {
+                doc = add_page_break(doc);
+            }
+            doc = add_version(doc, version, layout, title);
+        }
+        write_to_docx(doc, filepath);
+    } else {
+        for (i, version) in versions.iter().enumerate() {
+            write_to_docx(add_version(template(), version, layout, title), &version_path(filepath, i));
+        }
+    }
+
+    if !layout.answer_key {
+        return;
+    }
+    if sheet.single_file || sheet.combined_key || versions.len() == 1 {
+        let doc = versions.iter().fold(template(), |doc, version| add_answer_key(doc, version, layout, title));
+        write_to_docx(doc, &answer_key_path(filepath));
+    } else {
+        for (i, version) in versions.iter().enumerate() {
+            let doc = add_answer_key(template(), version, layout, title);
+            write_to_docx(doc, &answer_key_path(&version_path(filepath, i)));
+        }
+    }
+}
+
+pub fn header_font_size(version: &Version) -> usize {
+    version.sections.first().map(|s| s.font_size).unwrap_or(36)
+}
+
+fn add_version(mut doc: Docx, version: &Version, layout: &LayoutOpts, title: Option<&str>) -> Docx {
+    let font_size = header_font_size(version);
+    if let Some(title) = title {
+        doc = doc.add_paragraph(new_paragraph(font_size, title).bold().align(AlignmentType::Center))
+            .add_paragraph(new_paragraph(font_size, &header_line()));
+    }
+    if let Some(label) = &version.label {
+        doc = doc.add_paragraph(new_paragraph(font_size, label).align(AlignmentType::Right));
+    }
+    add_sections(doc, &version.sections, layout, false)
+}
+
+fn add_answer_key(doc: Docx, version: &Version, layout: &LayoutOpts, title: Option<&str>) -> Docx {
+    let doc = doc.add_paragraph(new_paragraph(header_font_size(version), &answer_key_heading(version, title)).bold());
+    add_sections(doc, &version.sections, layout, true)
+}
+
+fn add_page_break(doc: Docx) -> Docx {
+    doc.add_paragraph(Paragraph::new().add_run(Run::new().add_break(BreakType::Page)))
+}
+
+// 每个版本单独一个文件时的文件名：xxx.docx -> xxx-1.docx
+pub fn version_path(filepath: &str, i: usize) -> String {
+    insert_suffix(filepath, &format!("-{}", i + 1))
+}
+
+// xxx.docx -> xxx-answers.docx
+pub fn answer_key_path(filepath: &str) -> String {
+    insert_suffix(filepath, "-answers")
+}
+
+// 在文件名和扩展名之间插入后缀
+fn insert_suffix(filepath: &str, suffix: &str) -> String {
+    let name_start = filepath.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match filepath[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let (stem, extension) = filepath.split_at(name_start + dot);
+            format!("{}{}{}", stem, suffix, extension)
+        }
+        _ => format!("{}{}", filepath, suffix),
+    }
+}
+
+// 各小节每道题的题号，题号在各小节之间连续，并补齐到最宽的题号
+pub fn problem_labels(sections: &[Section], numbering: NumberStyle) -> Vec<Vec<String>> {
+    let total: usize = sections.iter().map(|s| s.problems.len()).sum();
+    let label_width = numbering.label(total).chars().count();
+
+    let mut n = 0;
+    sections.iter().map(|section| section.problems.iter().map(|_| {
+        n += 1;
+        let label = numbering.label(n);
+        if label.is_empty() { label } else { format!("{:<w$} ", label, w = label_width) }
+    }).collect()).collect()
+}
+
+// 依次写入各小节，题号在各小节之间连续；solved 为 true 时空白处填入答案
+// 题号样式以 layout 为准，作答区域样式由各小节自行决定
+pub fn add_sections(mut doc: Docx, sections: &[Section], layout: &LayoutOpts, solved: bool) -> Docx {
+    let labels = problem_labels(sections, layout.numbering);
+    for (section, labels) in sections.iter().zip(&labels) {
+        if let Some(heading) = &section.heading {
+            doc = doc.add_paragraph(new_paragraph(section.font_size, heading).bold());
+        }
+        let columns = section.columns.max(1) as usize;
+        for (line, labels) in section.problems.chunks(columns).zip(labels.chunks(columns)) {
+            if line.iter().any(Problem::has_table) {
+                doc = doc.add_table(nested_row(line, labels, section.font_size, solved));
+            } else if section.answer_style == AnswerStyle::Cell && !solved {
+                doc = doc.add_table(cell_row(line, labels, section.font_size));
+            } else if !line.iter().all(Problem::is_plain) {
+                doc = doc.add_paragraph(rich_paragraph(line, labels, section, solved));
+            } else {
+                doc = add_paragraph(doc, section.font_size, &line_text(line, labels, &section.answer_style, solved));
+            }
+            if section.spaced {
+                doc = doc.add_paragraph(Paragraph::new().size(section.font_size));
+            }
+        }
+    }
+    doc
+}
+
+// 一行题目的文本
+pub fn line_text(line: &[Problem], labels: &[String], style: &AnswerStyle, solved: bool) -> String {
+    let text: Vec<String> = line.iter().zip(labels.iter())
+        .map(|(p, label)| format!("{}{}", label, render_text(p, style, solved)))
+        .collect();
+    text.join(PROBLEM_SEPARATOR)
+}
+
+// 试卷开头的姓名、日期、得分栏
+pub fn header_line() -> String {
+    t("doc.header_line")
+}
+
+// 答案页的标题
+pub fn answer_key_heading(version: &Version, title: Option<&str>) -> String {
+    let heading = match title {
+        Some(title) => tf("doc.answers_of", &[("title", &title)]),
+        None => t("doc.answers"),
+    };
+    match &version.label {
+        Some(label) => format!("{} - {}", heading, label),
+        None => heading,
+    }
+}
+
+pub fn render_text(problem: &Problem, style: &AnswerStyle, solved: bool) -> String {
+    if solved {
+        return problem.solved_text();
+    }
+    problem.segments.iter().map(|segment| match segment {
+        Segment::Text(s) | Segment::Fraction(s) => s.clone(),
+        Segment::Blank(w) => style.blank(*w),
+        Segment::Clock { .. } => String::new(),
+        Segment::Bond { whole, parts } => {
+            let [whole, l, r] = bond_texts(whole, parts, || style.blank(2));
+            format!("{} = {} + {}", whole, l, r)
+        }
+        Segment::Grid { rows, columns, cells } => grid_text(&grid_texts(rows, columns, cells, || style.blank(2))),
+        Segment::Written { op, left, right } => written_text(*op, *left, *right, &style.blank(4)),
+    }).collect()
+}
+
+// 含有分数或钟面的一行题目，分数写成 Word 的 EQ 域，分子分母上下排列，答案页中的分数答案同样如此
+// 钟面是图片，答案页中画出指针
+fn rich_paragraph(line: &[Problem], labels: &[String], section: &Section, solved: bool) -> Paragraph {
+    let font_size = section.font_size;
+    let mut runs = vec![];
+    for (i, (problem, label)) in line.iter().zip(labels).enumerate() {
+        if i > 0 {
+            runs.push(text_run(font_size, PROBLEM_SEPARATOR));
+        }
+        runs.push(text_run(font_size, label));
+        let mut answers = problem.answers.iter();
+        for segment in &problem.segments {
+            match segment {
+                Segment::Text(s) => runs.push(text_run(font_size, s)),
+                Segment::Fraction(s) => runs.extend(fraction_runs(font_size, s)),
+                Segment::Blank(_) if solved => runs.extend(fraction_runs(font_size, answers.next().map(|a| a.as_str()).unwrap_or(""))),
+                Segment::Blank(w) => runs.push(text_run(font_size, &section.answer_style.blank(*w))),
+                Segment::Clock { minutes, hands } => runs.push(clock_run((*hands || solved).then_some(*minutes))),
+                Segment::Bond { whole, parts } => {
+                    let [whole, l, r] = bond_texts(whole, parts, || match solved {
+                        true => answers.next().cloned().unwrap_or_default(),
+                        false => section.answer_style.blank(2),
+                    });
+                    runs.push(text_run(font_size, &format!("{} = {} + {}", whole, l, r)));
+                }
+                Segment::Grid { rows, columns, cells } => {
+                    let texts = grid_texts(rows, columns, cells, || match solved {
+                        true => answers.next().cloned().unwrap_or_default(),
+                        false => section.answer_style.blank(2),
+                    });
+                    runs.push(text_run(font_size, &grid_text(&texts)));
+                }
+                Segment::Written { op, left, right } => {
+                    let answer = match solved {
+                        true => answers.next().cloned().unwrap_or_default(),
+                        false => section.answer_style.blank(4),
+                    };
+                    runs.push(text_run(font_size, &written_text(*op, *left, *right, &answer)));
+                }
+            }
+        }
+    }
+    runs.into_iter().fold(Paragraph::new().size(font_size), |p, run| p.add_run(run))
+}
+
+fn text_run(font_size: usize, text: &str) -> Run {
+    Run::new().size(font_size).fonts(RunFonts::new().ascii("Courier New")).add_text(text)
+}
+
+// "1 1/2" 的整数部分是普通文本，"n/d" 是 EQ \f(n,d) 域，域结果保留 n/d，不支持域的软件仍能显示
+fn fraction_runs(font_size: usize, text: &str) -> Vec<Run> {
+    let (whole, fraction) = match text.split_once(' ') {
+        Some((whole, fraction)) => (Some(whole), fraction),
+        None => (None, text),
+    };
+    let mut runs: Vec<Run> = whole.map(|w| text_run(font_size, w)).into_iter().collect();
+    match fraction.split_once('/') {
+        Some((num, den)) => runs.extend([
+            Run::new().size(font_size).add_field_char(FieldCharType::Begin, true),
+            Run::new().size(font_size).add_instr_text(InstrText::Unsupported(format!(" EQ \\f({},{}) ", num, den))),
+            Run::new().size(font_size).add_field_char(FieldCharType::Separate, false),
+            text_run(font_size, fraction),
+            Run::new().size(font_size).add_field_char(FieldCharType::End, false),
+        ]),
+        None => runs.push(text_run(font_size, fraction)),
+    }
+    runs
+}
+
+// 一行题目写成一行表格，空白处是带边框的单元格
+fn cell_row(line: &[Problem], labels: &[String], font_size: usize) -> Table {
+    let mut cells = vec![];
+    for (i, (problem, label)) in line.iter().zip(labels).enumerate() {
+        if i > 0 {
+            cells.push(TableCell::new().add_paragraph(new_paragraph(font_size, PROBLEM_SEPARATOR)));
+        }
+        if !label.is_empty() {
+            cells.push(TableCell::new().add_paragraph(new_paragraph(font_size, label)));
+        }
+        for segment in &problem.segments {
+            let cell = match segment {
+                Segment::Text(s) | Segment::Fraction(s) => TableCell::new().add_paragraph(new_paragraph(font_size, s)),
+                Segment::Blank(w) => TableCell::new()
+                    .set_borders(TableCellBorders::new())
+                    .add_paragraph(new_paragraph(font_size, &" ".repeat((*w).max(2) as usize))),
+                Segment::Clock { minutes, hands } => TableCell::new()
+                    .add_paragraph(Paragraph::new().add_run(clock_run(hands.then_some(*minutes)))),
+                Segment::Bond { whole, parts } => TableCell::new()
+                    .add_table(bond_table(bond_texts(whole, parts, String::new), font_size)).add_paragraph(Paragraph::new()),
+                Segment::Grid { rows, columns, cells } => TableCell::new()
+                    .add_table(grid_table(grid_texts(rows, columns, cells, String::new), font_size)).add_paragraph(Paragraph::new()),
+                Segment::Written { op, left, right } => TableCell::new()
+                    .add_table(written_table(written_cells(*op, *left, *right, false), font_size)).add_paragraph(Paragraph::new()),
+            };
+            cells.push(cell);
+        }
+    }
+    Table::without_borders(vec![TableRow::new(cells)])
+}
+
+// 含有分与合、方格或竖式的一行题目写成一行表格，每个分与合、方格、竖式是嵌套的小表格，其他片段各占一个单元格
+fn nested_row(line: &[Problem], labels: &[String], font_size: usize, solved: bool) -> Table {
+    let cell = |text: &str| TableCell::new().vertical_align(VAlignType::Center).add_paragraph(new_paragraph(font_size, text));
+    let mut cells = vec![];
+    for (i, (problem, label)) in line.iter().zip(labels).enumerate() {
+        if i > 0 {
+            cells.push(cell(PROBLEM_SEPARATOR));
+        }
+        if !label.is_empty() {
+            cells.push(cell(label));
+        }
+        let mut answers = problem.answers.iter();
+        let mut answer = || if solved { answers.next().cloned().unwrap_or_default() } else { String::new() };
+        for segment in &problem.segments {
+            cells.push(match segment {
+                Segment::Bond { whole, parts } => {
+                    // 单元格必须以段落结尾
+                    TableCell::new().add_table(bond_table(bond_texts(whole, parts, &mut answer), font_size)).add_paragraph(Paragraph::new())
+                }
+                Segment::Grid { rows, columns, cells } => {
+                    TableCell::new().add_table(grid_table(grid_texts(rows, columns, cells, &mut answer), font_size)).add_paragraph(Paragraph::new())
+                }
+                Segment::Written { op, left, right } => {
+                    // 竖式中已经写出答案
+                    answer();
+                    TableCell::new().add_table(written_table(written_cells(*op, *left, *right, solved), font_size)).add_paragraph(Paragraph::new())
+                }
+                Segment::Text(s) | Segment::Fraction(s) => cell(s),
+                Segment::Blank(w) if !solved => cell(&AnswerStyle::Plain.blank(*w)),
+                Segment::Blank(_) => cell(&answer()),
+                Segment::Clock { .. } => cell(""),
+            });
+        }
+    }
+    Table::without_borders(vec![TableRow::new(cells)])
+}
+
+// 整体在上，两个部分在下，中间用 / \ 连接，每个数是带边框的单元格，空白处是空的单元格
+fn bond_table([whole, l, r]: [String; 3], font_size: usize) -> Table {
+    // 每列约为两个数字加上边距的宽度(twip)，整体的单元格占两列
+    let width = font_size * 25;
+    let cell = |text: &str| TableCell::new().width(width, WidthType::Dxa).add_paragraph(new_paragraph(font_size, text).align(AlignmentType::Center));
+    let bordered = |text: &str| cell(text).set_borders(TableCellBorders::new());
+    Table::without_borders(vec![
+        TableRow::new(vec![bordered(&whole).width(width * 2, WidthType::Dxa).grid_span(2)]),
+        TableRow::new(vec![cell("/"), cell("\\")]),
+        TableRow::new(vec![bordered(&l), bordered(&r)]),
+    ]).set_grid(vec![width, width])
+}
+
+// 乘法表方格，每个单元格都有边框，表头加粗
+fn grid_table(texts: Vec<Vec<String>>, font_size: usize) -> Table {
+    // 每列约为三个数字加上边距的宽度(twip)，列数多时缩小到页面正文的宽度内(A4 默认页边距约 8500 twip)
+    let columns = texts.first().map(|row| row.len()).unwrap_or(0);
+    let width = (font_size * 35).min(8500 / columns.max(1));
+    let rows = texts.into_iter().enumerate().map(|(i, row)| {
+        TableRow::new(row.into_iter().enumerate().map(|(j, text)| {
+            let paragraph = new_paragraph(font_size, &text).align(AlignmentType::Center);
+            let paragraph = if i == 0 || j == 0 { paragraph.bold() } else { paragraph };
+            TableCell::new().width(width, WidthType::Dxa).add_paragraph(paragraph)
+        }).collect())
+    }).collect();
+    Table::new(rows).set_grid(vec![width; columns])
+}
+
+// 竖式，每个数字占一格，格子是浅色的虚线，竖式中的横线和除号是实线
+fn written_table(cells: Vec<Vec<WrittenCell>>, font_size: usize) -> Table {
+    // 每列约为一个数字加上边距的宽度(twip)
+    let width = font_size * 15;
+    let columns = cells.first().map(|row| row.len()).unwrap_or(0);
+    let guide = |position| TableCellBorder::new(position).border_type(BorderType::Dotted).color("BFBFBF");
+    let solid = |position| TableCellBorder::new(position).size(12);
+    let rows = cells.into_iter().map(|row| {
+        TableRow::new(row.into_iter().map(|cell| {
+            let borders = TableCellBorders::with_empty()
+                .set(if cell.top { solid(TableCellBorderPosition::Top) } else { guide(TableCellBorderPosition::Top) })
+                .set(if cell.left { solid(TableCellBorderPosition::Left) } else { guide(TableCellBorderPosition::Left) })
+                .set(guide(TableCellBorderPosition::Bottom))
+                .set(guide(TableCellBorderPosition::Right));
+            TableCell::new().width(width, WidthType::Dxa).set_borders(borders)
+                .add_paragraph(new_paragraph(font_size, &cell.text).align(AlignmentType::Center))
+        }).collect())
+    }).collect();
+    Table::without_borders(rows).set_grid(vec![width; columns])
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+    use crate::{AnswerStyle, LayoutOpts, NumberStyle, SheetOpts};
+    use crate::layout::{answer_key_path, render_text, version_path};
+    use crate::problem::Problem;
+
+    #[test]
+    fn test_label() {
+        assert_eq!(NumberStyle::None.label(3), "");
+        assert_eq!(NumberStyle::Arabic.label(17), "17.");
+        assert_eq!(NumberStyle::Circled.label(1), "①");
+        assert_eq!(NumberStyle::Circled.label(20), "⑳");
+        assert_eq!(NumberStyle::Circled.label(21), "㉑");
+        assert_eq!(NumberStyle::Circled.label(50), "㊿");
+        assert_eq!(NumberStyle::Circled.label(51), "(51)");
+        assert_eq!(NumberStyle::Letter.label(1), "(a)");
+        assert_eq!(NumberStyle::Letter.label(26), "(z)");
+        assert_eq!(NumberStyle::Letter.label(28), "(ab)");
+    }
+
+    #[test]
+    fn test_parse_answer_style() {
+        assert_eq!("plain".parse::<AnswerStyle>(), Ok(AnswerStyle::Plain));
+        assert_eq!("underline:6".parse::<AnswerStyle>(), Ok(AnswerStyle::Underline(6)));
+        assert_eq!("box".parse::<AnswerStyle>(), Ok(AnswerStyle::Box));
+        assert!("underline:x".parse::<AnswerStyle>().is_err());
+        assert!("circle".parse::<AnswerStyle>().is_err());
+    }
+
+    #[test]
+    fn test_render_text() {
+        let p = Problem::expr(" 3 + 4 =".to_string(), "7".to_string());
+        assert_eq!(render_text(&p, &AnswerStyle::Plain, false), " 3 + 4 = ");
+        assert_eq!(render_text(&p, &AnswerStyle::Underline(3), false), " 3 + 4 = ___");
+        assert_eq!(render_text(&p, &AnswerStyle::Plain, true), " 3 + 4 = 7");
+        let layout = LayoutOpts::default();
+        assert_eq!(render_text(&p, &layout.answer_style, false), " 3 + 4 = ");
+    }
+
+    #[test]
+    fn test_answer_key_path() {
+        assert_eq!(answer_key_path("./output/add-minus.docx"), "./output/add-minus-answers.docx");
+        assert_eq!(answer_key_path(&version_path("./output/add-minus.docx", 1)), "./output/add-minus-2-answers.docx");
+        assert_eq!(answer_key_path("./output/graded.html"), "./output/graded-answers.html");
+        assert_eq!(version_path("./output/add-minus", 0), "./output/add-minus-1");
+    }
+
+    #[test]
+    fn test_gen_versions() {
+        let sheet = SheetOpts { seed: Some(10), copies: 3, ..Default::default() };
+        let mut seeds = vec![];
+        let versions = sheet.gen_versions(|rng| {
+            seeds.push(rng.random::<u64>());
+            Ok(vec![])
+        }).unwrap();
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions[2].label.as_deref(), Some("版本 3 (seed 12)"));
+        // 各版本的随机数不同，同一个种子可以重现
+        assert_ne!(seeds[0], seeds[1]);
+        let single = SheetOpts { seed: Some(11), ..Default::default() };
+        let versions = single.gen_versions(|rng| {
+            assert_eq!(rng.random::<u64>(), seeds[1]);
+            Ok(vec![])
+        }).unwrap();
+        assert!(versions[0].label.is_none());
+    }
+}
@@ -1,4 +1,5 @@
 // 最佳实践： 对于二进制类型crate， 配合 tests/integraton_test 集成测试
 // 导出对应的方法，供集成测试调用
-mod err;
-mod utils;
+pub mod err;
+pub mod utils;
+pub mod i18n;
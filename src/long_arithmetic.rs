@@ -0,0 +1,328 @@
+use rand::Rng;
+use crate::{LongDivisionOpts, LongMultiplicationOpts};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 竖式中的一格，top 为上边的横线，left 为左边的竖线(除法的除号)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WrittenCell {
+    pub text: String,
+    pub top: bool,
+    pub left: bool,
+}
+
+// 演算中的一行：数的最后一位在第 end 列，line 为这一行上方横线的起止列
+struct Step {
+    text: String,
+    end: usize,
+    line: Option<(usize, usize)>,
+}
+
+// 竖式的各行各列，solved 为 false 时只写出运算数，演算的行留空
+pub fn written_cells(op: Op, left: i64, right: i64, solved: bool) -> Vec<Vec<WrittenCell>> {
+    match op {
+        Op::Div => division_cells(left, right, solved),
+        _ => multiplication_cells(left, right, solved),
+    }
+}
+
+// 数字右对齐，最后一位在第 end 列
+fn number_row(width: usize, text: &str, end: usize) -> Vec<WrittenCell> {
+    let mut row = vec![WrittenCell::default(); width];
+    let start = end + 1 - text.chars().count();
+    for (i, c) in text.chars().enumerate() {
+        row[start + i].text = c.to_string();
+    }
+    row
+}
+
+// 演算的各行，题目中只保留行数，不写数字和横线
+fn step_rows(width: usize, steps: Vec<Step>, solved: bool) -> Vec<Vec<WrittenCell>> {
+    steps.into_iter().map(|step| {
+        if !solved {
+            return vec![WrittenCell::default(); width];
+        }
+        let mut row = number_row(width, &step.text, step.end);
+        if let Some((start, end)) = step.line {
+            row[start..=end].iter_mut().for_each(|cell| cell.top = true);
+        }
+        row
+    }).collect()
+}
+
+//     3 4
+//   × 2 7
+//   -----
+//   2 3 8
+//   6 8
+//   -----
+//   9 1 8
+// 第二个因数中为 0 的数位不写部分积，只有一个部分积时直接写出积
+fn multiplication_cells(left: i64, right: i64, solved: bool) -> Vec<Vec<WrittenCell>> {
+    let product = left * right;
+    let width = [left, right, product].iter().map(|n| n.to_string().len()).max().unwrap() + 1;
+    let mut rows = vec![number_row(width, &left.to_string(), width - 1)];
+    let mut row = number_row(width, &right.to_string(), width - 1);
+    row[0].text = Op::Mul.symbol().to_string();
+    rows.push(row);
+
+    let partials: Vec<(usize, i64)> = right.to_string().chars().rev()
+        .map(|c| c.to_digit(10).unwrap() as i64)
+        .enumerate()
+        .filter(|(_, digit)| *digit != 0)
+        .collect();
+    let line = Some((0, width - 1));
+    let mut steps = vec![];
+    if partials.len() > 1 {
+        for (i, (position, digit)) in partials.iter().enumerate() {
+            steps.push(Step { text: (left * digit).to_string(), end: width - 1 - position, line: if i == 0 { line } else { None } });
+        }
+    }
+    steps.push(Step { text: product.to_string(), end: width - 1, line });
+    rows.extend(step_rows(width, steps, solved));
+    // 运算数下面的横线属于题目
+    rows[2].iter_mut().for_each(|cell| cell.top = true);
+    rows
+}
+
+//       1 4 4
+//     ┌──────
+//   6 │ 8 6 4
+//       6
+//       ---
+//       2 6
+//       2 4
+//       ---
+//         2 4
+//         2 4
+//         ---
+//           0
+// 商中间的 0 不写乘积，直接落下下一位
+fn division_cells(dividend: i64, divisor: i64, solved: bool) -> Vec<Vec<WrittenCell>> {
+    let digits: Vec<i64> = dividend.to_string().chars().map(|c| c.to_digit(10).unwrap() as i64).collect();
+    let offset = divisor.to_string().len();
+    let width = offset + digits.len();
+
+    let quotient = if solved { (dividend / divisor).to_string() } else { String::new() };
+    let mut rows = vec![number_row(width, &quotient, width - 1)];
+    let mut row = number_row(width, &dividend.to_string(), width - 1);
+    row[..offset].clone_from_slice(&number_row(offset, &divisor.to_string(), offset - 1));
+    row[offset..].iter_mut().for_each(|cell| cell.top = true);
+    row[offset].left = true;
+    rows.push(row);
+
+    // 从够除的最高位开始
+    let mut i = 0;
+    let mut current = digits[0];
+    while current < divisor && i + 1 < digits.len() {
+        i += 1;
+        current = current * 10 + digits[i];
+    }
+    let mut steps: Vec<Step> = vec![];
+    loop {
+        let q = current / divisor;
+        if q > 0 {
+            let product = (q * divisor).to_string();
+            let end = offset + i;
+            steps.push(Step { line: None, end, text: product.clone() });
+            let line = Some((end + 1 - product.len(), end));
+            let remainder = current - q * divisor;
+            if i + 1 == digits.len() {
+                steps.push(Step { text: remainder.to_string(), end, line });
+                break;
+            }
+            i += 1;
+            current = remainder * 10 + digits[i];
+            steps.push(Step { text: current.to_string(), end: offset + i, line });
+        } else if i + 1 < digits.len() {
+            i += 1;
+            current = current * 10 + digits[i];
+            if let Some(step) = steps.last_mut() {
+                step.text = current.to_string();
+                step.end = offset + i;
+            }
+        } else {
+            // 最后一位商为 0，余数已经写出
+            break;
+        }
+    }
+    rows.extend(step_rows(width, steps, solved));
+    rows
+}
+
+// 随机的 digits 位数，一位数时不取 0 和 1
+fn random_number(digits: u32, rng: &mut impl Rng) -> i64 {
+    if digits == 1 {
+        return rng.random_range(2..=9);
+    }
+    rng.random_range(10i64.pow(digits - 1)..10i64.pow(digits))
+}
+
+impl LongMultiplicationOpts {
+    pub fn gen_long_multiplication_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/long-multiplication.docx", Source::new("long-multiplication", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        (0..self.count).map(|_| {
+            let left = random_number(self.left_digits, rng);
+            let right = random_number(self.right_digits, rng);
+            Problem { segments: vec![Segment::Written { op: Op::Mul, left, right }], answers: vec![(left * right).to_string()], fact: None }
+                .with_fact(left, Op::Mul, right)
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.long_multiplication")
+    }
+}
+
+impl LongDivisionOpts {
+    pub fn gen_long_division_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/long-division.docx", Source::new("long-division", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        // 除数的位数少于被除数
+        let divisor_digits = self.divisor_digits.min(self.dividend_digits - 1);
+        (0..self.count).map(|_| {
+            let divisor = random_number(divisor_digits, rng);
+            let dividend = if self.remainder {
+                random_number(self.dividend_digits, rng)
+            } else {
+                // 商的范围使被除数恰好是 dividend_digits 位
+                let min = (10i64.pow(self.dividend_digits - 1) + divisor - 1) / divisor;
+                let max = (10i64.pow(self.dividend_digits) - 1) / divisor;
+                rng.random_range(min..=max) * divisor
+            };
+            let (quotient, remainder) = (dividend / divisor, dividend % divisor);
+            let answer = if remainder == 0 {
+                quotient.to_string()
+            } else {
+                tf("long_division.remainder", &[("quotient", &quotient), ("remainder", &remainder)])
+            };
+            Problem { segments: vec![Segment::Written { op: Op::Div, left: dividend, right: divisor }], answers: vec![answer], fact: None }
+                .with_fact(dividend, Op::Div, divisor)
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.long_division")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{LongDivisionOpts, LongMultiplicationOpts};
+    use crate::long_arithmetic::{written_cells, WrittenCell};
+    use crate::problem::{Op, Segment};
+    use crate::utils::new_rng;
+
+    // 各行的数字，空格为空的格子
+    fn texts(cells: &[Vec<WrittenCell>]) -> Vec<String> {
+        cells.iter().map(|row| row.iter().map(|c| if c.text.is_empty() { " ".to_string() } else { c.text.clone() }).collect()).collect()
+    }
+
+    // 各行中有横线的列
+    fn lines(cells: &[Vec<WrittenCell>]) -> Vec<String> {
+        cells.iter().map(|row| row.iter().map(|c| if c.top { '-' } else { ' ' }).collect()).collect()
+    }
+
+    #[test]
+    fn test_multiplication_cells() {
+        let cells = written_cells(Op::Mul, 34, 27, true);
+        assert_eq!(texts(&cells), vec!["  34", "× 27", " 238", " 68 ", " 918"]);
+        assert_eq!(lines(&cells), vec!["    ", "    ", "----", "    ", "----"]);
+
+        // 为 0 的数位不写部分积
+        let cells = written_cells(Op::Mul, 125, 304, true);
+        assert_eq!(texts(&cells), vec!["   125", "×  304", "   500", " 375  ", " 38000"]);
+        let cells = written_cells(Op::Mul, 34, 20, true);
+        assert_eq!(texts(&cells), vec!["  34", "× 20", " 680"]);
+
+        // 题目中演算的行留空，只有运算数下面的横线
+        let cells = written_cells(Op::Mul, 34, 27, false);
+        assert_eq!(texts(&cells), vec!["  34", "× 27", "    ", "    ", "    "]);
+        assert_eq!(lines(&cells), vec!["    ", "    ", "----", "    ", "    "]);
+    }
+
+    #[test]
+    fn test_division_cells() {
+        let cells = written_cells(Op::Div, 864, 6, true);
+        assert_eq!(texts(&cells), vec![" 144", "6864", " 6  ", " 26 ", " 24 ", "  24", "  24", "   0"]);
+        assert_eq!(lines(&cells), vec!["    ", " ---", "    ", " -  ", "    ", " -- ", "    ", "  --"]);
+        assert!(cells[1][1].left && !cells[1][0].left);
+
+        // 商中间的 0 直接落下下一位，最后一位不够除时余数已经写出
+        let cells = written_cells(Op::Div, 6184, 3, true);
+        assert_eq!(texts(&cells), vec![" 2061", "36184", " 6   ", "  18 ", "  18 ", "    4", "    3", "    1"]);
+        let cells = written_cells(Op::Div, 420, 4, true);
+        assert_eq!(texts(&cells), vec![" 105", "4420", " 4  ", "  20", "  20", "   0"]);
+        let cells = written_cells(Op::Div, 250, 12, true);
+        assert_eq!(texts(&cells), vec!["   20", "12250", "  24 ", "   10"]);
+
+        let cells = written_cells(Op::Div, 864, 6, false);
+        assert_eq!(texts(&cells)[..2], ["    ", "6864"]);
+        assert_eq!(cells.len(), 8);
+    }
+
+    #[test]
+    fn test_gen_problems() {
+        let opts = LongMultiplicationOpts {
+            count: 20,
+            left_digits: 3,
+            right_digits: 2,
+            ..Default::default()
+        };
+        for problem in opts.gen_problems(&mut new_rng(Some(16))) {
+            let Segment::Written { op: Op::Mul, left, right } = problem.segments[0] else { panic!("expect long multiplication") };
+            assert!((100..1000).contains(&left) && (10..100).contains(&right));
+            assert_eq!(problem.answers[0], (left * right).to_string());
+        }
+
+        let opts = LongDivisionOpts {
+            count: 20,
+            dividend_digits: 3,
+            divisor_digits: 1,
+            ..Default::default()
+        };
+        for problem in opts.gen_problems(&mut new_rng(Some(17))) {
+            let Segment::Written { op: Op::Div, left, right } = problem.segments[0] else { panic!("expect long division") };
+            assert!((100..1000).contains(&left) && (2..10).contains(&right));
+            assert_eq!(left % right, 0);
+            assert_eq!(problem.answers[0], (left / right).to_string());
+        }
+
+        let opts = LongDivisionOpts { remainder: true, dividend_digits: 4, divisor_digits: 2, ..opts };
+        for problem in opts.gen_problems(&mut new_rng(Some(18))) {
+            let Segment::Written { left, right, .. } = problem.segments[0] else { panic!("expect long division") };
+            let expected = if left % right == 0 { format!("{}", left / right) } else { format!("{}……{}", left / right, left % right) };
+            assert_eq!(problem.answers[0], expected);
+        }
+    }
+}
@@ -2,11 +2,39 @@ mod err;
 mod utils;
 mod add_minus;
 mod missing_number;
+mod problem;
+mod layout;
+mod worksheet;
+mod batch;
+mod quiz;
+mod progress;
+mod adaptive;
+mod daily;
+mod grade;
+mod manifest;
+mod serve;
+mod i18n;
+mod fraction;
+mod money;
+mod time;
+mod clock;
+mod units;
+mod word_problem;
+mod number_bond;
+mod place_value;
+mod rounding;
+mod times_table;
+mod long_arithmetic;
 
-use std::ops::Range;
-use clap::{Args, Parser, Subcommand};
-use crate::add_minus::{gen_arithmetic_to_docx, gen_arithmetic_to_docx_by_pattern1, gen_arithmetic_to_docx_by_pattern2, gen_arithmetic_to_docx_by_pattern3, gen_arithmetic_to_docx_by_pattern4};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::adaptive::FactWeights;
+use crate::add_minus::gen_arithmetic_to_docx;
+use crate::fraction::Fraction;
 use crate::utils::{create_dir_if_necessary};
+use crate::word_problem::Templates;
 
 // 全局初始化一次的变量
 // static OPERAND_PATTERN: OnceLock<&str> = OnceLock::new();
@@ -14,30 +42,37 @@ use crate::utils::{create_dir_if_necessary};
 fn main() {
     init();
 
-    let cli = Cli::parse();
-    match &cli.command {
-        Some(Commands::AddMinus(add_minus)) => {
-            // default 30 expressions per page
-            if add_minus.category.ends_with("p1") {
-                // p1: add(result [6, 16])
-                gen_arithmetic_to_docx_by_pattern1(add_minus)
-            } else if add_minus.category.ends_with("p2") {
-                // p2: minus(start with 8, 10, 15~18)
-                gen_arithmetic_to_docx_by_pattern2(add_minus)
-            } else if add_minus.category.ends_with("p3") {
-                // p3: minus(start with 11~14)
-                gen_arithmetic_to_docx_by_pattern3(add_minus)
-            } else if add_minus.category.ends_with("p4") {
-                // p4: minus(start with 4~9)
-                gen_arithmetic_to_docx_by_pattern4(add_minus)
-            } else {
-                gen_arithmetic_to_docx(add_minus);
-            }
-        },
-        Some(Commands::MissingNumber(missing_number)) => {
-            missing_number.gen_missing_numbers_to_docx();
-        },
-        None => {}
+    // 帮助信息在解析命令行时就需要，所以先确定语言
+    let args: Vec<String> = std::env::args().collect();
+    i18n::init(&i18n::detect(i18n::lang_arg(&args)));
+    let matches = i18n::localize_command(Cli::command()).get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let result = match &cli.command {
+        Some(Commands::AddMinus(add_minus)) => gen_arithmetic_to_docx(add_minus),
+        Some(Commands::MissingNumber(missing_number)) => missing_number.gen_missing_numbers_to_docx(),
+        Some(Commands::Fractions(fractions)) => fractions.gen_fractions_to_docx(),
+        Some(Commands::Time(time)) => time.gen_time_to_docx(),
+        Some(Commands::Units(units)) => units.gen_units_to_docx(),
+        Some(Commands::WordProblems(word_problems)) => word_problems.gen_word_problems_to_docx(),
+        Some(Commands::NumberBonds(number_bonds)) => number_bonds.gen_number_bonds_to_docx(),
+        Some(Commands::PlaceValue(place_value)) => place_value.gen_place_value_to_docx(),
+        Some(Commands::Rounding(rounding)) => rounding.gen_rounding_to_docx(),
+        Some(Commands::TimesTable(times_table)) => times_table.gen_times_table_to_docx(),
+        Some(Commands::LongMultiplication(long_multiplication)) => long_multiplication.gen_long_multiplication_to_docx(),
+        Some(Commands::LongDivision(long_division)) => long_division.gen_long_division_to_docx(),
+        Some(Commands::Worksheet(worksheet)) => worksheet.gen_worksheet_to_docx(),
+        Some(Commands::Quiz(quiz)) => quiz.run_quiz(),
+        Some(Commands::Daily(daily)) => daily.run(),
+        Some(Commands::Grade(grade)) => grade.grade_to_file(),
+        Some(Commands::Render(render)) => render.render(),
+        Some(Commands::Serve(serve)) => serve.serve(),
+        Some(Commands::Progress(progress)) => progress.run(),
+        Some(Commands::Batch(batch)) => batch.gen_batch_to_docx(),
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", i18n::tf("error.prefix", &[("error", &e)]));
+        std::process::exit(1);
     }
 }
 
@@ -53,6 +88,9 @@ fn init() {
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    // 界面和文档的语言，比如 zh, en，默认按环境变量确定
+    #[arg(long, global = true)]
+    lang: Option<String>,
 
 }
 #[derive(Subcommand, Debug)]
@@ -61,9 +99,114 @@ enum Commands {
     AddMinus(AddMinusOpts),
     /// 补充缺失的数字
     MissingNumber(MissingNumberOpts),
+    /// 分数加减法，答案化为最简分数或带分数
+    Fractions(FractionsOpts),
+    /// 时间：经过的时间、认识钟表
+    Time(TimeOpts),
+    /// 单位换算和复名数加减法，比如 3米45厘米 + 2米70厘米
+    Units(UnitsOpts),
+    /// 应用题：按模板生成加减法的文字题，比如 小明有5个苹果，送给同学2个苹果，还剩多少个苹果？
+    WordProblems(WordProblemsOpts),
+    /// 数的分与合以及一图四式，整体和部分取自 p1~p4
+    NumberBonds(NumberBondsOpts),
+    /// 数位与计数单位：4,372 = 4个千 + 3个百 + 7个十 + 2个一、展开式以及数字表示多少
+    PlaceValue(PlaceValueOpts),
+    /// 近似数与估算：347 精确到十位约是 350，先求近似数再估算 398 + 215
+    Rounding(RoundingOpts),
+    /// 乘法表：带空白的乘法表方格、某一张表的口算练习以及填满一行或一列
+    TimesTable(TimesTableOpts),
+    /// 乘法竖式：每个数字占一格，答案页写出部分积
+    LongMultiplication(LongMultiplicationOpts),
+    /// 除法竖式：每个数字占一格，答案页写出每一步
+    LongDivision(LongDivisionOpts),
+    /// 多个小节组成的综合练习，题号连续，答案页合并
+    Worksheet(WorksheetOpts),
+    /// 按描述文件(TOML/JSON)批量生成试卷
+    Batch(BatchOpts),
+    /// 在终端中逐题练习，参数与对应的生成器相同，比如 quiz add-minus -n 20 -c + -r 20
+    Quiz(QuizOpts),
+    /// 查看或导出学生的练习记录，按题型统计正确率和用时
+    Progress(ProgressOpts),
+    /// 按间隔复习(Leitner)安排每个学生当天的加减法练习，到期的事实优先，不足时补充新的
+    Daily(DailyOpts),
+    /// 批改：按种子和参数重新生成试卷，与孩子的答案比对，比如 grade -a answers.csv add-minus -n 40 -c + --seed 42
+    Grade(GradeOpts),
+    /// 把生成文档时保存的清单 xxx.json 重新输出为 docx 或 html
+    Render(RenderOpts),
+    /// 启动本地网页，在浏览器中填写参数、预览并下载试卷和答案页，不需要联网
+    Serve(ServeOpts),
 }
 
-#[derive(Args, Debug)]
+// 可以作为试卷小节的生成器
+#[derive(Subcommand, Debug, Clone, Serialize)]
+#[serde(tag = "generator", rename_all = "kebab-case")]
+enum Generator {
+    /// 加减法
+    AddMinus(AddMinusOpts),
+    /// 补充缺失的数字
+    MissingNumber(MissingNumberOpts),
+    /// 分数加减法，答案化为最简分数或带分数
+    Fractions(FractionsOpts),
+    /// 时间：经过的时间、认识钟表
+    Time(TimeOpts),
+    /// 单位换算和复名数加减法，比如 3米45厘米 + 2米70厘米
+    Units(UnitsOpts),
+    /// 应用题：按模板生成加减法的文字题，比如 小明有5个苹果，送给同学2个苹果，还剩多少个苹果？
+    WordProblems(WordProblemsOpts),
+    /// 数的分与合以及一图四式，整体和部分取自 p1~p4
+    NumberBonds(NumberBondsOpts),
+    /// 数位与计数单位：4,372 = 4个千 + 3个百 + 7个十 + 2个一、展开式以及数字表示多少
+    PlaceValue(PlaceValueOpts),
+    /// 近似数与估算：347 精确到十位约是 350，先求近似数再估算 398 + 215
+    Rounding(RoundingOpts),
+    /// 乘法表：带空白的乘法表方格、某一张表的口算练习以及填满一行或一列
+    TimesTable(TimesTableOpts),
+    /// 乘法竖式：每个数字占一格，答案页写出部分积
+    LongMultiplication(LongMultiplicationOpts),
+    /// 除法竖式：每个数字占一格，答案页写出每一步
+    LongDivision(LongDivisionOpts),
+}
+
+#[derive(Args, Debug, Serialize)]
+struct WorksheetOpts {
+    // 每个小节是一个生成器子命令及其参数，可用 --heading 指定小节标题，默认按类别生成，比如
+    // -s "--heading 一、进位加法 add-minus -n 20 -c + -r 99" -s "missing-number -n 5"
+    // 各小节的 --numbering 和 -k 不生效，以本命令的为准
+    #[arg(short, long="section", required=true, allow_hyphen_values=true)]
+    #[serde(skip)]
+    sections: Vec<String>,
+
+    // 试卷标题，指定后同时输出姓名、日期、得分栏
+    #[arg(short, long)]
+    title: Option<String>,
+
+    // 输出文件
+    #[arg(short, long, default_value="./output/worksheet.docx")]
+    output: String,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+// 小节的命令行
+#[derive(Parser, Debug, Clone, Serialize)]
+#[command(no_binary_name = true)]
+struct SectionCli {
+    #[arg(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heading: Option<String>,
+
+    #[command(subcommand)]
+    #[serde(flatten)]
+    generator: Generator,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
 struct AddMinusOpts {
     // 生成多少个算式，默认40个
     #[arg(short='n', long, default_value_t=40)]
@@ -98,44 +241,342 @@ struct AddMinusOpts {
     // *：左右操作数相同，是范围内的任意随机数
     // 5*：左右操作数相同，是5的倍数
     // =10：满足运算结果等于10
-    #[arg(short='p', long, allow_hyphen_values=true, default_value="*,*")]
+    // 负数常数写成 -3 或 (-3)，比如 *,-3 或 -5~-1，范围中的 - 在开头或括号中时是负号
+    #[arg(short='p', long, allow_hyphen_values=true, default_value="*,*", value_parser=add_minus::validate_operand_pattern)]
     operand_pattern: String,
 
+    // 小数的位数：N 表示左右操作数都是N位小数，L,R 分别指定左右操作数，结果的位数取两者中较多的
+    // 使用小数时，-l -r -b -e 和 C* 都以最小的小数单位计，比如 --decimals 2 -r 999 表示不超过 9.99
+    #[arg(long, value_parser=add_minus::validate_decimals)]
+    decimals: Option<String>,
+
+    // 金额加减法：cny(¥, 元角分) | usd($, 美元美分)，-l -r -b -e 和 -p 都以分计，比如 -r 5000 表示不超过 ¥50.00
+    // 操作数是最小的常用硬币的倍数：人民币 1角，美元 1美分
+    #[arg(long, value_enum, conflicts_with="decimals")]
+    money: Option<Currency>,
+
+    // 金额写成复名数，比如 3元5角，而不是 ¥3.50
+    #[arg(long, default_value_t=false, requires="money")]
+    money_units: bool,
+
+    // 找零：全部是减法，被减数是一张纸币(1~100元)，不受 -l -r 限制，比如 ¥50.00 - ¥12.30 =
+    // 固定用某一张纸币时改用 -p，比如 -c _ -p 5000,*
+    #[arg(long, default_value_t=false, requires="money")]
+    change: bool,
+
     // 参与运算的数的范围最小值，默认是0
-    #[arg(short='l', long, default_value_t=0)]
-    number_min_inclusive: u16,
+    #[arg(short='l', long, allow_negative_numbers=true, default_value_t=0)]
+    number_min_inclusive: i64,
 
     // 参与运算的数的范围最大值
-    #[arg(short='r', long, default_value_t=10)]
-    number_max_inclusive: u16,
+    #[arg(short='r', long, allow_negative_numbers=true, default_value_t=10)]
+    number_max_inclusive: i64,
 
-    // 允许的运算结果最小值，默认是0
-    #[arg(short='b', long, default_value_t=0)]
-    result_min_inclusive: i16,
+    // 允许的运算结果最小值，默认是0，允许负数的结果时需要调低
+    #[arg(short='b', long, allow_negative_numbers=true, default_value_t=0)]
+    result_min_inclusive: i64,
 
     // 允许的运算结果最大值，默认是99
-    #[arg(short='e', long, default_value_t = 99)]
-    result_max_inclusive: i16,
+    #[arg(short='e', long, allow_negative_numbers=true, default_value_t = 99)]
+    result_max_inclusive: i64,
 
     // 写入到docx中的字体大小
     #[arg(short='f', long, default_value_t = 56)]
     output_docx_font_size: u16,
+
+    // 自适应出题：按学生的作答结果调整各题型出现的频率，错得多、用时长的题型出现得更多
+    // 结果文件可以是练习记录 ./data/progress/<student>.jsonl，progress export --raw 导出的 csv/json，
+    // 或者包含 left,op,right,correct[,elapsed_ms] 列的 csv
+    // 仍然受 -c -p -l -r 等参数约束
+    #[arg(long, value_parser=FactWeights::load)]
+    adaptive: Option<FactWeights>,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+// 题号及作答区域，加减法和补充数字共用
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+struct LayoutOpts {
+    // 题号样式： none | arabic(1.) | circled(①) | letter((a))，题号在整份试卷中连续，与答案页一致
+    #[arg(long, value_enum, default_value_t=NumberStyle::None)]
+    numbering: NumberStyle,
+
+    // 作答区域样式：
+    // plain: 保持原样，加减法只有 =， 补充数字是与数字等宽的下划线
+    // underline:N: N个字符的下划线，省略N则为4
+    // box: 方框
+    // cell: 题目写成表格，作答处为空白单元格
+    #[arg(long, default_value="plain")]
+    answer_style: AnswerStyle,
+
+    // 同时生成答案页，文件名为 xxx-answers.docx
+    #[arg(short='k', long, default_value_t=false)]
+    answer_key: bool,
+}
+
+// 整份试卷的生成参数
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+struct SheetOpts {
+    // 随机数种子，相同的种子和参数生成相同的题目；不指定则随机选取并打印出来
+    #[arg(long)]
+    seed: Option<u64>,
+
+    // 生成多少个版本，题型和难度相同、数字不同，第i个版本的种子为 seed + i - 1
+    #[arg(long, default_value_t=1)]
+    copies: u16,
+
+    // 所有版本写入同一个文件，版本之间分页；否则每个版本一个文件 xxx-1.docx, xxx-2.docx ...
+    #[arg(long, default_value_t=false)]
+    single_file: bool,
+
+    // 每个版本一个文件时，答案页仍然合并为一个文件 xxx-answers.docx
+    #[arg(long, default_value_t=false)]
+    combined_key: bool,
+}
+
+#[derive(Args, Debug)]
+struct QuizOpts {
+    // 学生名字，指定后作答记录追加到该学生的练习记录中
+    #[arg(short='u', long)]
+    student: Option<String>,
+
+    #[command(flatten)]
+    store: StoreOpts,
+
+    #[command(subcommand)]
+    generator: Generator,
+}
+
+// 练习记录的存放位置
+#[derive(Args, Debug, Clone, Serialize)]
+struct StoreOpts {
+    // 数据目录，每个学生的记录保存在 <data-dir>/progress/<student>.jsonl
+    #[arg(long, default_value="./data")]
+    data_dir: PathBuf,
+}
+
+#[derive(Args, Debug, Serialize)]
+struct DailyOpts {
+    // 学生名字，复习计划保存在 <data-dir>/schedule/<student>.json
+    #[arg(short='u', long)]
+    student: String,
+
+    // 每天多少道题
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 在终端中练习，作答结果直接更新复习计划和练习记录；否则生成 ./output/daily-<student>-<date>.docx
+    #[arg(long, default_value_t=false, conflicts_with="record")]
+    quiz: bool,
+
+    // 登记纸质练习的作答结果并更新复习计划，不生成练习；文件格式与 add-minus --adaptive 相同，
+    // 生成练习时会在 docx 旁边生成一份 daily-<student>-<date>.csv，在 correct 列中把做错的题改为 false 即可
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    // 按哪个时区计算日期，相对 UTC 的小时数，默认 8 即北京时间
+    #[arg(long, allow_negative_numbers=true, default_value_t=8, value_parser=clap::value_parser!(i64).range(-12..=14))]
+    utc_offset: i64,
+
+    // 每页多少列
+    #[arg(short='o', long, default_value_t=3)]
+    column_per_page: u16,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 56)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    store: StoreOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug)]
+struct GradeOpts {
+    // 孩子的答案：.csv 按顺序每个单元格一道题，其他文件每行一道题；一道题有多个空时用空格分隔，留空表示未作答
+    #[arg(short, long)]
+    answers: PathBuf,
+
+    // 生成时使用了 --copies 时，批改第几个版本
+    #[arg(long, default_value_t=1)]
+    version: u16,
+
+    // 批改结果的格式
+    #[arg(long, value_enum, default_value_t=OutputFormat::Docx)]
+    format: OutputFormat,
+
+    // 输出文件，默认 ./output/graded.docx 或 ./output/graded.html
+    #[arg(short, long)]
+    output: Option<String>,
+
+    // 生成试卷时保存的清单 xxx.json，指定后不需要生成器参数
+    #[arg(short, long)]
+    manifest: Option<PathBuf>,
+
+    // 生成试卷时的生成器及参数，必须包括 --seed
+    #[command(subcommand)]
+    generator: Option<Generator>,
+}
+
+#[derive(Args, Debug)]
+struct ServeOpts {
+    // 监听的地址，默认只允许本机访问
+    #[arg(long, default_value="127.0.0.1")]
+    host: String,
+
+    // 端口
+    #[arg(short, long, default_value_t=8080)]
+    port: u16,
+}
+
+#[derive(Args, Debug)]
+struct RenderOpts {
+    // 清单文件
+    manifest: PathBuf,
+
+    // 输出格式
+    #[arg(long, value_enum, default_value_t=OutputFormat::Docx)]
+    format: OutputFormat,
+
+    // 输出文件，默认与清单同名
+    #[arg(short, long)]
+    output: Option<String>,
+
+    // 同时输出答案页，清单中已指定 -k 时总是输出
+    #[arg(short='k', long, default_value_t=false)]
+    answer_key: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Docx,
+    Html,
+}
+
+#[derive(Args, Debug)]
+struct ProgressOpts {
+    #[command(subcommand)]
+    command: ProgressCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ProgressCommand {
+    /// 按题型(比如 加9、进位加法)打印正确率和平均用时，正确率低的在前
+    Show(ProgressShowOpts),
+    /// 导出题型统计或原始记录
+    Export(ProgressExportOpts),
+}
+
+#[derive(Args, Debug)]
+struct ProgressShowOpts {
+    // 学生名字
+    #[arg(short='u', long)]
+    student: String,
+
+    #[command(flatten)]
+    store: StoreOpts,
+}
+
+#[derive(Args, Debug)]
+struct ProgressExportOpts {
+    // 学生名字
+    #[arg(short='u', long)]
+    student: String,
+
+    // 导出格式
+    #[arg(long, value_enum, default_value_t=ExportFormat::Csv)]
+    format: ExportFormat,
+
+    // 导出原始作答记录，而不是题型统计
+    #[arg(long, default_value_t=false)]
+    raw: bool,
+
+    // 输出文件，不指定则打印到终端
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    #[command(flatten)]
+    store: StoreOpts,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Args, Debug)]
+struct BatchOpts {
+    // 描述文件，按扩展名区分 .toml 或 .json，字段与各命令的长参数名一致（- 换成 _），比如
+    // [[worksheet]]
+    // output = "daily.docx"
+    // seed = 42
+    // copies = 2
+    // numbering = "arabic"
+    // [[worksheet.section]]
+    // generator = "add-minus"
+    // count = 20
+    // category = "+"
+    // number_max_inclusive = 99
+    #[arg(required=true)]
+    specs: Vec<PathBuf>,
+}
+
+// 金额加减法的货币
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Currency {
+    Cny,
+    Usd,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum NumberStyle {
+    #[default]
+    None,
+    Arabic,
+    Circled,
+    Letter,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum AnswerStyle {
+    #[default]
+    Plain,
+    Underline(u16),
+    Box,
+    Cell,
 }
 
 // 操作数配置
 enum OperandConfig {
     TwoOperand(OperandPattern, OperandPattern), // L,R
     OneOperand(OperandPattern), // L，是L,L简化
-    Result(u16) // =A
+    Result(i64) // =A
 }
 enum OperandPattern {
     Wildcard,
-    NumberWildcard(u16),
-    Constant(u16),
-    ConstantRange(Range<u16>)
+    NumberWildcard(i64),
+    Constant(i64),
+    ConstantRange(RangeInclusive<i64>)
 }
 
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Clone, Serialize)]
 struct MissingNumberOpts {
     // 生成多少个，默认10个
     #[arg(short='n', long, default_value_t=10)]
@@ -151,7 +592,7 @@ struct MissingNumberOpts {
 
     // 递进
     #[arg(short, long, allow_negative_numbers=true, default_value_t=1)]
-    step: i16,
+    step: i64,
 
     // 随机产生数据起始是step的倍数，比如step是5， 则10,15,20符合, 11, 16, 21不符合，因为11不是5的倍数
     #[arg(short='t', long, default_value_t=false)]
@@ -163,14 +604,569 @@ struct MissingNumberOpts {
 
     // 参与数的范围最小值，默认是0
     #[arg(short='l', long, default_value_t=0)]
-    number_min_inclusive: u16,
+    number_min_inclusive: u64,
 
     // 参与数的范围最大值，此处只是一个参考值，允许略微超过此上限
     #[arg(short='r', long, default_value_t=100)]
-    number_max_inclusive: u16,
+    number_max_inclusive: u64,
 
     // 写入到docx中的字体大小，需要与 line_width 配合，字体太大，则line_width需减少，否则一行容纳不下
     #[arg(short='f', long, default_value_t = 36)]
     output_docx_font_size: u16,
+
+    // 最大的题号，用于计算题号占用的宽度，默认为 count；worksheet 中题号在各小节之间连续，由 worksheet 设置
+    #[arg(skip)]
+    #[serde(skip)]
+    label_max: Option<usize>,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct FractionsOpts {
+    // 生成多少个算式，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认2列
+    #[arg(short='o', long, default_value_t=2)]
+    column_per_page: u16,
+
+    // 类别： +: 全部加法， _: 全部减法， 其他任何: 随机混合加减法
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 可选的分母，逗号分隔，每个在 2 到 1000 之间
+    #[arg(short, long, value_delimiter=',', default_value="2,3,4,5,6,8,10,12", value_parser=clap::value_parser!(u64).range(2..=fraction::MAX_DENOMINATOR))]
+    denominators: Vec<u64>,
+
+    // 两个分数的分母相同(同分母加减)，否则分母各自随机选取
+    #[arg(short, long, default_value_t=false)]
+    same_denominator: bool,
+
+    // 运算数可以是带分数，比如 2 1/3
+    #[arg(short, long, default_value_t=false)]
+    mixed: bool,
+
+    // 带分数整数部分的最大值
+    #[arg(long, default_value_t=3)]
+    max_whole: u16,
+
+    // 允许的运算结果最小值，可以写成分数，比如 1/2；减法的结果总是不小于0
+    #[arg(short='b', long, allow_negative_numbers=true, default_value="0")]
+    result_min_inclusive: Fraction,
+
+    // 允许的运算结果最大值，可以写成分数或带分数，比如 "2 1/2"
+    #[arg(short='e', long, allow_negative_numbers=true, default_value="10")]
+    result_max_inclusive: Fraction,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 32)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
 }
 
+#[derive(Args, Debug, Clone, Serialize)]
+struct TimeOpts {
+    // 生成多少个题目，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认2列
+    #[arg(short='o', long, default_value_t=2)]
+    column_per_page: u16,
+
+    // 类别：
+    // +: 经过一段时间后是几时，比如 9:40 经过 35分钟 是
+    // _: 一段时间之前是几时
+    // e: 两个时刻之间经过了多长时间
+    // 其他任何: 随机混合 + 和 _
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 12 或 24 小时制
+    #[arg(long, value_enum, default_value_t=HourFormat::H24)]
+    hour_format: HourFormat,
+
+    // 时刻和经过的时间都是这么多分钟的倍数
+    #[arg(short='s', long, default_value_t=5, value_parser=clap::value_parser!(u16).range(1..=60))]
+    minute_step: u16,
+
+    // 经过的时间最多多少分钟，时刻都在同一天之内
+    #[arg(short='e', long, default_value_t=180, value_parser=clap::value_parser!(u16).range(1..1440))]
+    max_elapsed: u16,
+
+    // 分钟必须进位(超过60)或退位
+    #[arg(long, default_value_t=false)]
+    carry: bool,
+
+    // 钟面题：none 不画钟面 | read 读出钟面上的时间 | draw 按时间在空白钟面上画出指针，答案页中画好指针
+    // 使用钟面时 -c --hour-format -e --carry 不生效
+    #[arg(long, value_enum, default_value_t=ClockMode::None)]
+    clock: ClockMode,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 32)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+enum HourFormat {
+    #[value(name = "24")]
+    #[serde(rename = "24")]
+    H24,
+    #[value(name = "12")]
+    #[serde(rename = "12")]
+    H12,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ClockMode {
+    None,
+    Read,
+    Draw,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct UnitsOpts {
+    // 生成多少个题目，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认2列
+    #[arg(short='o', long, default_value_t=2)]
+    column_per_page: u16,
+
+    // 类别：c: 单位换算，+: 复名数加法，_: 复名数减法，其他任何: 随机混合
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 单位，逗号分隔，每道题随机选取一种：length(米/厘米) | mass(千克/克) | volume(升/毫升) | time(小时/分钟)
+    #[arg(short='u', long, value_enum, value_delimiter=',', default_value="length,mass,volume,time")]
+    families: Vec<UnitFamily>,
+
+    // 以小单位计的量都是 step 的倍数，比如 5 表示 3米45厘米 而不会是 3米47厘米
+    #[arg(short, long, default_value_t=5, value_parser=clap::value_parser!(i64).range(1..))]
+    step: i64,
+
+    // 参与运算的量的范围最小值，以大单位计
+    #[arg(short='l', long, default_value_t=0)]
+    number_min_inclusive: i64,
+
+    // 参与运算的量的范围最大值，以大单位计，比如 10 表示不超过10米
+    #[arg(short='r', long, default_value_t=10)]
+    number_max_inclusive: i64,
+
+    // 允许的运算结果最小值，以大单位计
+    #[arg(short='b', long, default_value_t=0)]
+    result_min_inclusive: i64,
+
+    // 允许的运算结果最大值，以大单位计
+    #[arg(short='e', long, default_value_t=20)]
+    result_max_inclusive: i64,
+
+    // 复名数加减法中小单位必须进位或退位，比如 45厘米 + 70厘米
+    #[arg(long, default_value_t=false)]
+    carry: bool,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 32)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum UnitFamily {
+    Length,
+    Mass,
+    Volume,
+    Time,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct WordProblemsOpts {
+    // 生成多少个题目，默认10个
+    #[arg(short='n', long, default_value_t=10)]
+    count: u16,
+
+    // 每页多少列，默认1列
+    #[arg(short='o', long, default_value_t=1)]
+    column_per_page: u16,
+
+    // 类别：+: 加法的模板，_: 减法的模板，其他任何: 全部模板
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 模板文件(TOML)，格式见 resources/word_problems/zh.toml；指定目录时按语言选取其中的 <lang>.toml
+    // 不指定则使用当前语言的内置模板
+    #[arg(short='t', long, value_parser=Templates::load)]
+    templates: Option<Templates>,
+
+    // 运算数的模式，与 add-minus -p 相同
+    #[arg(short='p', long, allow_hyphen_values=true, default_value="*,*", value_parser=add_minus::validate_operand_pattern)]
+    operand_pattern: String,
+
+    // 参与运算的数的范围最小值，默认是1，避免 送给同学0个苹果 这样的题目
+    #[arg(short='l', long, default_value_t=1)]
+    number_min_inclusive: i64,
+
+    // 参与运算的数的范围最大值
+    #[arg(short='r', long, default_value_t=20)]
+    number_max_inclusive: i64,
+
+    // 允许的运算结果最小值
+    #[arg(short='b', long, default_value_t=0)]
+    result_min_inclusive: i64,
+
+    // 允许的运算结果最大值
+    #[arg(short='e', long, default_value_t=40)]
+    result_max_inclusive: i64,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 28)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct NumberBondsOpts {
+    // 生成多少个题目，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认分与合每行4个，一图四式每行1个
+    #[arg(short='o', long)]
+    column_per_page: Option<u16>,
+
+    // 类别：b: 分(已知整体，求一个部分)，w: 合(已知两个部分，求整体)，f: 一图四式，其他任何: 分与合随机混合
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 整体和部分取自哪些预设，逗号分隔，与 add-minus -c p1~p4 相同
+    #[arg(short='s', long, value_enum, value_delimiter=',', default_value="p1,p2,p3,p4")]
+    presets: Vec<Preset>,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 32)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct PlaceValueOpts {
+    // 生成多少个题目，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认1列
+    #[arg(short='o', long, default_value_t=1)]
+    column_per_page: u16,
+
+    // 类别：d: 写成计数单位，比如 4,372 = __个千 + 3个百 + __个十 + 2个一
+    // e: 展开式，比如 4,372 = 4000 + __ + 70 + 2
+    // v: 数字表示多少，比如 4,372 中的 7 表示 __
+    // 其他任何: 随机混合
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 数的位数
+    #[arg(short='d', long, default_value_t=4, value_parser=clap::value_parser!(u32).range(2..=place_value::MAX_DIGITS as i64))]
+    digits: u32,
+
+    // 中间的数位至少有一个 0，比如 4,072，位数不少于3时生效
+    #[arg(short='z', long, default_value_t=false)]
+    zeros: bool,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 32)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct RoundingOpts {
+    // 生成多少个题目，默认20个
+    #[arg(short='n', long, default_value_t=20)]
+    count: u16,
+
+    // 每页多少列，默认2列
+    #[arg(short='o', long, default_value_t=2)]
+    column_per_page: u16,
+
+    // 类别：r: 求近似数，比如 347 精确到十位约是 __
+    // e: 估算加减法，比如 398 + 215 ≈ __ + __ = __，+ 只估算加法，_ 只估算减法
+    // 其他任何: 随机混合
+    #[arg(short, long, default_value="x")]
+    category: String,
+
+    // 精确到哪些数位，逗号分隔，每题随机选取一个
+    #[arg(short='t', long="to", value_enum, value_delimiter=',', default_value="ten,hundred")]
+    places: Vec<RoundPlace>,
+
+    // 恰好在一半时的取舍：half-up 四舍五入，half-even 四舍六入五成双(取偶数)
+    #[arg(long, value_enum, default_value_t=RoundingRule::HalfUp)]
+    rule: RoundingRule,
+
+    // 题目中的数的范围最小值
+    #[arg(short='l', long, default_value_t=10, value_parser=clap::value_parser!(i64).range(..=rounding::MAX_NUMBER))]
+    number_min_inclusive: i64,
+
+    // 题目中的数的范围最大值，不超过 10^18
+    #[arg(short='r', long, default_value_t=999, value_parser=clap::value_parser!(i64).range(..=rounding::MAX_NUMBER))]
+    number_max_inclusive: i64,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 28)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct TimesTableOpts {
+    // 生成多少个题目，默认方格1个，填一行或一列4个，口算20个
+    #[arg(short='n', long)]
+    count: Option<u16>,
+
+    // 每页多少列，默认方格和填一行每行1个，填一列每行4个，口算每行4个
+    #[arg(short='o', long)]
+    column_per_page: Option<u16>,
+
+    // 类别：g: 乘法表方格，随机空出一些格子
+    // r: 填一行，7 乘以打乱顺序的 1~--max 的积写成一行；c: 填一列，同样的积写成一列
+    // 其他任何: 口算，比如 7 × 6 =
+    #[arg(short, long, default_value="d")]
+    category: String,
+
+    // 乘法表的第一个因数(方格的各行)，逗号分隔，默认 1~--max
+    #[arg(short='t', long, value_delimiter=',', value_parser=clap::value_parser!(u32).range(1..=99))]
+    tables: Vec<u32>,
+
+    // 第二个因数(方格的各列)从1到多少
+    #[arg(short='m', long, default_value_t=12, value_parser=clap::value_parser!(u32).range(1..=99))]
+    max: u32,
+
+    // 方格中空白的格子所占的百分比，较难的乘法更容易被空出
+    #[arg(short='b', long, default_value_t=50, value_parser=clap::value_parser!(u8).range(1..=100))]
+    blanks: u8,
+
+    // 方格的表头打乱顺序
+    #[arg(short='s', long, default_value_t=false)]
+    shuffle: bool,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 28)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct LongMultiplicationOpts {
+    // 生成多少个题目，默认6个
+    #[arg(short='n', long, default_value_t=6)]
+    count: u16,
+
+    // 每页多少列，默认3列
+    #[arg(short='o', long, default_value_t=3)]
+    column_per_page: u16,
+
+    // 第一个因数的位数
+    #[arg(short='l', long, default_value_t=2, value_parser=clap::value_parser!(u32).range(1..=6))]
+    left_digits: u32,
+
+    // 第二个因数的位数
+    #[arg(short='r', long, default_value_t=2, value_parser=clap::value_parser!(u32).range(1..=4))]
+    right_digits: u32,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 28)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+#[derive(Args, Debug, Clone, Serialize)]
+struct LongDivisionOpts {
+    // 生成多少个题目，默认6个
+    #[arg(short='n', long, default_value_t=6)]
+    count: u16,
+
+    // 每页多少列，默认3列
+    #[arg(short='o', long, default_value_t=3)]
+    column_per_page: u16,
+
+    // 被除数的位数
+    #[arg(short='d', long, default_value_t=3, value_parser=clap::value_parser!(u32).range(2..=7))]
+    dividend_digits: u32,
+
+    // 除数的位数，少于被除数的位数
+    #[arg(short='s', long, default_value_t=1, value_parser=clap::value_parser!(u32).range(1..=3))]
+    divisor_digits: u32,
+
+    // 允许有余数，答案写成 144……2
+    #[arg(short='m', long, default_value_t=false)]
+    remainder: bool,
+
+    // 写入到docx中的字体大小
+    #[arg(short='f', long, default_value_t = 28)]
+    output_docx_font_size: u16,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    layout: LayoutOpts,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    sheet: SheetOpts,
+}
+
+// add-minus 的 p1~p4：p1 和为 6~18 的加法，p2 被减数为 11~18，p3 被减数为 10、11，p4 被减数为 4~9
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Preset {
+    P1,
+    P2,
+    P3,
+    P4,
+}
+
+// rounding --to 的数位
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RoundPlace {
+    Ten,
+    Hundred,
+    Thousand,
+    TenThousand,
+}
+
+// 恰好在两个近似数中间时的取舍规则
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RoundingRule {
+    // 五入，负数时远离 0
+    HalfUp,
+    // 取偶数，比如精确到十位时 25 -> 20，35 -> 40
+    HalfEven,
+}
+
+// 与命令行的默认值相同的参数，其他生成器和测试只需写出不同的字段，比如 AddMinusOpts { count: 1, ..Default::default() }
+// 必填的参数在方括号中给出
+macro_rules! default_from_clap {
+    ($($opts:ident [$($arg:expr),*]),* $(,)?) => {$(
+        impl Default for $opts {
+            fn default() -> Self {
+                let matches = $opts::augment_args(clap::Command::new("default")).get_matches_from(["default" $(, $arg)*]);
+                $opts::from_arg_matches(&matches).expect("clap default values")
+            }
+        }
+    )*};
+}
+
+default_from_clap! {
+    AddMinusOpts ["--category", "x"],
+    LayoutOpts [],
+    SheetOpts [],
+    MissingNumberOpts [],
+    FractionsOpts [],
+    TimeOpts [],
+    UnitsOpts [],
+    WordProblemsOpts [],
+    NumberBondsOpts [],
+    PlaceValueOpts [],
+    RoundingOpts [],
+    TimesTableOpts [],
+    LongMultiplicationOpts [],
+    LongDivisionOpts [],
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+    use crate::{i18n, AddMinusOpts, Cli, FractionsOpts, SheetOpts, TimeOpts, UnitsOpts};
+
+    #[test]
+    fn test_catalogs_complete() {
+        let missing = i18n::missing_messages(&Cli::command());
+        assert!(missing.is_empty(), "missing messages: {:?}", missing);
+    }
+
+    #[test]
+    fn test_default_from_clap() {
+        assert_eq!(SheetOpts::default().copies, 1);
+        assert_eq!(TimeOpts::default().minute_step, 5);
+        assert_eq!(FractionsOpts::default().denominators, vec![2, 3, 4, 5, 6, 8, 10, 12]);
+        assert!(!UnitsOpts::default().families.is_empty());
+        assert_eq!(AddMinusOpts::default().category, "x");
+    }
+}
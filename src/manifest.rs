@@ -0,0 +1,295 @@
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::i18n::{lang, t};
+use crate::{AnswerStyle, LayoutOpts, OutputFormat, RenderOpts, SheetOpts};
+use crate::err::Error;
+use crate::layout::{answer_key_heading, answer_key_path, header_font_size, line_text, problem_labels, version_path, write_docx, header_line, Version, PROBLEM_SEPARATOR};
+use crate::clock::clock_svg;
+use crate::long_arithmetic::{written_cells, WrittenCell};
+use crate::problem::{bond_texts, grid_texts, Problem, Segment};
+use crate::utils::{escape_html, write};
+
+// 生成文档的命令及其全部参数
+pub struct Source {
+    pub command: String,
+    pub options: Value,
+}
+
+impl Source {
+    pub fn new(command: &str, options: &impl Serialize) -> Source {
+        Source { command: command.to_string(), options: serde_json::to_value(options).unwrap_or(Value::Null) }
+    }
+}
+
+// 与生成的文档一起保存的清单，记录生成方式和全部题目及答案，可以用 render 重新输出
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Manifest {
+    pub tool: String,
+    pub tool_version: String,
+    // 生成器命令，比如 add-minus, worksheet
+    pub command: String,
+    // 命令的全部参数，字段名与命令行的长参数名一致（- 换成 _）
+    pub options: Value,
+    // 第一个版本的种子
+    pub seed: u64,
+    pub title: Option<String>,
+    pub layout: LayoutOpts,
+    pub sheet: SheetOpts,
+    pub versions: Vec<Version>,
+}
+
+impl Manifest {
+    pub fn new(source: Source, layout: &LayoutOpts, sheet: &SheetOpts, title: Option<&str>, versions: Vec<Version>) -> Manifest {
+        Manifest {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            command: source.command,
+            options: source.options,
+            seed: versions.first().map(|v| v.seed).unwrap_or(0),
+            title: title.map(|t| t.to_string()),
+            layout: layout.clone(),
+            sheet: sheet.clone(),
+            versions,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Manifest, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    // 每个文档旁边一个清单：xxx.docx -> xxx.json，每个版本单独一个文件时各自一个清单
+    pub fn save(&self, filepath: &str) -> Result<(), Error> {
+        if self.sheet.single_file || self.versions.len() == 1 {
+            return write(&serde_json::to_string_pretty(self)?, &manifest_path(filepath));
+        }
+        for (i, version) in self.versions.iter().enumerate() {
+            let manifest = Manifest { seed: version.seed, versions: vec![version.clone()], ..self.clone() };
+            write(&serde_json::to_string_pretty(&manifest)?, &manifest_path(&version_path(filepath, i)))?;
+        }
+        Ok(())
+    }
+
+    // 全部题目，按版本、小节的顺序
+    pub fn problems(&self, version: usize) -> Option<Vec<Problem>> {
+        self.versions.get(version).map(|v| v.sections.iter().flat_map(|s| s.problems.clone()).collect())
+    }
+}
+
+pub fn manifest_path(filepath: &str) -> String {
+    Path::new(filepath).with_extension("json").to_string_lossy().to_string()
+}
+
+impl RenderOpts {
+    pub fn render(&self) -> Result<(), Error> {
+        let manifest = Manifest::load(&self.manifest)?;
+        let mut layout = manifest.layout.clone();
+        layout.answer_key |= self.answer_key;
+        let title = manifest.title.as_deref();
+        let output = self.output.clone().unwrap_or_else(|| {
+            let extension = match self.format {
+                OutputFormat::Docx => "docx",
+                OutputFormat::Html => "html",
+            };
+            self.manifest.with_extension(extension).to_string_lossy().to_string()
+        });
+
+        match self.format {
+            OutputFormat::Docx => {
+                // 已经按版本拆分的清单不再拆分
+                let sheet = SheetOpts { single_file: true, ..manifest.sheet.clone() };
+                write_docx(&manifest.versions, &sheet, &layout, title, &output);
+            }
+            OutputFormat::Html => {
+                write(&to_html(&manifest.versions, &layout, title, false), &output)?;
+                if layout.answer_key {
+                    write(&to_html(&manifest.versions, &layout, title, true), &answer_key_path(&output))?;
+                }
+                println!("{}", t("doc.generated_html"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// 所有版本写入同一个 html，打印时版本之间分页
+pub fn to_html(versions: &[Version], layout: &LayoutOpts, title: Option<&str>, solved: bool) -> String {
+    let mut html = format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n", lang());
+    html.push_str(&format!("<title>{}</title>\n<style>\n", escape_html(title.unwrap_or("gen_arithmatic"))));
+    html.push_str("body { font-family: \"Courier New\", monospace; }\np { white-space: pre; }\n");
+    html.push_str(".version + .version { page-break-before: always; }\n.cell { border: 1px solid; display: inline-block; min-width: 1.5em; }\n");
+    html.push_str(".bond { display: inline-grid; grid-template-columns: auto auto; text-align: center; vertical-align: middle; }\n");
+    html.push_str(".bond span { border: 1px solid; min-width: 1.5em; }\n.bond .whole { grid-column: span 2; justify-self: center; }\n.bond .link { border: none; }\n");
+    html.push_str(".grid { display: inline-grid; text-align: center; vertical-align: middle; }\n.grid span { border: 1px solid; min-width: 2em; }\n.grid .header { font-weight: bold; }\n");
+    html.push_str(".written { display: inline-grid; text-align: center; vertical-align: top; }\n.written span { border: 1px dotted #bfbfbf; min-width: 1em; min-height: 1.2em; }\n");
+    html.push_str(".written .top { border-top: 2px solid black; }\n.written .left { border-left: 2px solid black; }\n");
+    html.push_str("</style>\n</head>\n<body>\n");
+    for version in versions {
+        // docx 中的字号是半磅
+        let font_size = header_font_size(version) as f64 / 2.0;
+        html.push_str(&format!("<div class=\"version\" style=\"font-size: {}pt\">\n", font_size));
+        if solved {
+            html.push_str(&format!("<h2>{}</h2>\n", escape_html(&answer_key_heading(version, title))));
+        } else {
+            if let Some(title) = title {
+                html.push_str(&format!("<h1 style=\"text-align: center\">{}</h1>\n<p>{}</p>\n", escape_html(title), header_line()));
+            }
+            if let Some(label) = &version.label {
+                html.push_str(&format!("<p style=\"text-align: right\">{}</p>\n", escape_html(label)));
+            }
+        }
+
+        let labels = problem_labels(&version.sections, layout.numbering);
+        for (section, labels) in version.sections.iter().zip(&labels) {
+            let style = format!(" style=\"font-size: {}pt\"", section.font_size as f64 / 2.0);
+            if let Some(heading) = &section.heading {
+                html.push_str(&format!("<h3{}>{}</h3>\n", style, escape_html(heading)));
+            }
+            let columns = section.columns.max(1) as usize;
+            for (line, labels) in section.problems.chunks(columns).zip(labels.chunks(columns)) {
+                let text = if section.answer_style == AnswerStyle::Cell && !solved {
+                    cell_line_html(line, labels)
+                } else if !line.iter().all(Problem::is_plain) {
+                    rich_line_html(line, labels, &section.answer_style, solved)
+                } else {
+                    escape_html(&line_text(line, labels, &section.answer_style, solved))
+                };
+                html.push_str(&format!("<p{}>{}</p>\n", style, text));
+                if section.spaced {
+                    html.push_str(&format!("<p{}> </p>\n", style));
+                }
+            }
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// 含有钟面的一行题目，钟面是 svg 图片，答案页中画出指针
+fn rich_line_html(line: &[Problem], labels: &[String], style: &AnswerStyle, solved: bool) -> String {
+    let problems: Vec<String> = line.iter().zip(labels).map(|(problem, label)| {
+        let mut answers = problem.answers.iter();
+        let segments: String = problem.segments.iter().map(|segment| match segment {
+            Segment::Text(s) | Segment::Fraction(s) => escape_html(s),
+            Segment::Blank(_) if solved => escape_html(answers.next().map(|a| a.as_str()).unwrap_or("")),
+            Segment::Blank(w) => escape_html(&style.blank(*w)),
+            Segment::Clock { minutes, hands } => clock_svg((*hands || solved).then_some(*minutes)),
+            Segment::Bond { whole, parts } => bond_html(bond_texts(whole, parts, || match solved {
+                true => answers.next().cloned().unwrap_or_default(),
+                false => String::new(),
+            })),
+            Segment::Grid { rows, columns, cells } => grid_html(grid_texts(rows, columns, cells, || match solved {
+                true => answers.next().cloned().unwrap_or_default(),
+                false => String::new(),
+            })),
+            Segment::Written { op, left, right } => {
+                // 竖式中已经写出答案
+                answers.next();
+                written_html(written_cells(*op, *left, *right, solved))
+            }
+        }).collect();
+        format!("{}{}", escape_html(label), segments)
+    }).collect();
+    problems.join(PROBLEM_SEPARATOR)
+}
+
+// 作答处为带边框的空白
+fn cell_line_html(line: &[Problem], labels: &[String]) -> String {
+    let problems: Vec<String> = line.iter().zip(labels).map(|(problem, label)| {
+        let segments: String = problem.segments.iter().map(|segment| match segment {
+            Segment::Text(s) | Segment::Fraction(s) => escape_html(s),
+            Segment::Blank(w) => format!("<span class=\"cell\">{}</span>", " ".repeat((*w).max(2) as usize)),
+            Segment::Clock { minutes, hands } => clock_svg(hands.then_some(*minutes)),
+            Segment::Bond { whole, parts } => bond_html(bond_texts(whole, parts, String::new)),
+            Segment::Grid { rows, columns, cells } => grid_html(grid_texts(rows, columns, cells, String::new)),
+            Segment::Written { op, left, right } => written_html(written_cells(*op, *left, *right, false)),
+        }).collect();
+        format!("{}{}", escape_html(label), segments)
+    }).collect();
+    problems.join(PROBLEM_SEPARATOR)
+}
+
+// 与 docx 中的表格相同：整体在上，两个部分在下
+fn bond_html([whole, l, r]: [String; 3]) -> String {
+    format!("<span class=\"bond\"><span class=\"whole\">{}</span><span class=\"link\">/</span><span class=\"link\">\\</span><span>{}</span><span>{}</span></span>",
+            escape_html(&whole), escape_html(&l), escape_html(&r))
+}
+
+// 与 docx 中的表格相同：每个单元格都有边框，表头加粗
+fn grid_html(texts: Vec<Vec<String>>) -> String {
+    let columns = texts.first().map(|row| row.len()).unwrap_or(0);
+    let cells: String = texts.iter().enumerate().flat_map(|(i, row)| row.iter().enumerate().map(move |(j, text)| {
+        let class = if i == 0 || j == 0 { " class=\"header\"" } else { "" };
+        format!("<span{}>{}</span>", class, escape_html(text))
+    })).collect();
+    format!("<span class=\"grid\" style=\"grid-template-columns: repeat({}, auto)\">{}</span>", columns, cells)
+}
+
+// 与 docx 中的表格相同：每个数字占一格，横线和除号是实线
+fn written_html(cells: Vec<Vec<WrittenCell>>) -> String {
+    let columns = cells.first().map(|row| row.len()).unwrap_or(0);
+    let spans: String = cells.iter().flatten().map(|cell| {
+        let classes: Vec<&str> = [(cell.top, "top"), (cell.left, "left")].iter().filter(|(on, _)| *on).map(|(_, class)| *class).collect();
+        let class = if classes.is_empty() { String::new() } else { format!(" class=\"{}\"", classes.join(" ")) };
+        format!("<span{}>{}</span>", class, escape_html(&cell.text))
+    }).collect();
+    format!("<span class=\"written\" style=\"grid-template-columns: repeat({}, auto)\">{}</span>", columns, spans)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AnswerStyle, LayoutOpts, NumberStyle, SheetOpts};
+    use crate::layout::{Section, Version};
+    use crate::manifest::{manifest_path, to_html, Manifest, Source};
+    use crate::problem::Problem;
+
+    fn versions() -> Vec<Version> {
+        (0..2).map(|i| Version {
+            label: Some(format!("版本 {} (seed {})", i + 1, 42 + i)),
+            seed: 42 + i,
+            sections: vec![Section {
+                heading: Some("一、加法".to_string()),
+                problems: vec![Problem::expr("3 + 4 =".to_string(), "7".to_string()), Problem::expr("1 < 2 =".to_string(), "?".to_string())],
+                columns: 2,
+                font_size: 40,
+                spaced: false,
+                answer_style: AnswerStyle::Underline(3),
+            }],
+        }).collect()
+    }
+
+    #[test]
+    fn test_manifest_json() {
+        let layout = LayoutOpts { numbering: NumberStyle::Arabic, ..Default::default() };
+        let sheet = SheetOpts { seed: Some(42), copies: 2, ..Default::default() };
+        let manifest = Manifest::new(Source::new("add-minus", &sheet), &layout, &sheet, Some("练习"), versions());
+        assert_eq!(manifest.seed, 42);
+        assert_eq!(manifest.options["copies"], 2);
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(json.contains("\"answer_style\":\"underline:3\""));
+        assert!(json.contains("\"numbering\":\"arabic\""));
+        let loaded: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.versions[1].sections[0].problems, manifest.versions[1].sections[0].problems);
+        assert_eq!(loaded.layout.answer_style, AnswerStyle::Plain);
+        assert_eq!(loaded.problems(1).unwrap().len(), 2);
+        assert!(loaded.problems(2).is_none());
+
+        assert_eq!(manifest_path("./output/add-minus-1.docx"), "./output/add-minus-1.json");
+    }
+
+    #[test]
+    fn test_to_html() {
+        let layout = LayoutOpts { numbering: NumberStyle::Arabic, ..Default::default() };
+        let html = to_html(&versions(), &layout, Some("练习"), false);
+        assert!(html.contains("<p style=\"font-size: 20pt\">1. 3 + 4 = ___      2. 1 &lt; 2 = ___</p>"));
+        assert!(html.contains("<p style=\"text-align: right\">版本 2 (seed 43)</p>"));
+        assert_eq!(html.matches("class=\"version\"").count(), 2);
+
+        let html = to_html(&versions(), &layout, Some("练习"), true);
+        assert!(html.contains("<h2>练习 答案 - 版本 1 (seed 42)</h2>"));
+        assert!(html.contains("1. 3 + 4 = 7"));
+    }
+}
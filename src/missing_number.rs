@@ -1,37 +1,54 @@
 use std::cmp::{max, min};
-use docx_rs::{Paragraph};
-use rand::{rng, Rng};
+use rand::Rng;
 use rand::distr::Uniform;
 use crate::MissingNumberOpts;
-use crate::utils::{add_paragraph, char_len, read_from_docx, write, write_to_docx};
+use crate::err::Error;
+use crate::layout::{render_text, Section};
+use crate::manifest::Source;
+use crate::problem::{Problem, Segment};
+use crate::utils::{char_len, new_rng, write};
 
 impl MissingNumberOpts {
-    pub fn gen_missing_numbers_to_docx(&self) {
-        let mut doc = read_from_docx("./resources/template.docx");
+    pub fn gen_missing_numbers_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/missing-numbers.docx", Source::new("missing-number", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
 
-        for _i in 0..self.count {
-            let line = &self.gen_single_missing_numbers();
-            let font_size = self.output_docx_font_size as usize;
-            doc = add_paragraph(doc, font_size, line);
-            doc = doc.add_paragraph(Paragraph::new().size(font_size))
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: 1,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
         }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        (0..self.count).map(|_| self.gen_single_missing_numbers(rng)).collect()
+    }
 
-        write_to_docx(doc, "./output/missing-numbers.docx");
+    // 扣除题号所占宽度后，一行可以容纳的字符数
+    fn effective_line_width(&self) -> u16 {
+        let label_max = self.label_max.unwrap_or(self.count as usize);
+        let label_width = self.layout.numbering.label(label_max).chars().count() as u16;
+        if label_width == 0 { self.line_width } else { self.line_width.saturating_sub(label_width + 1) }
     }
 
-    fn gen_single_missing_numbers(&self) -> String {
+    fn gen_single_missing_numbers(&self, rng: &mut impl Rng) -> Problem {
         let mut gaps = vec![];
-        self.gen_gaps(&mut gaps);
+        self.gen_gaps(&mut gaps, rng);
         // 所有的gap对应的number数量
-        let all_gap_numbers = gaps.iter().fold(0, |acc, &x| acc + x);
+        let all_gap_numbers = gaps.iter().sum::<u16>();
         // 满足能插入所有gap 所需要的最少number数量（每个gap至少间隔一个数字）
         let min_numbers = all_gap_numbers + gaps.len() as u16 - 1;
 
         // 生成数字
-        let mut numbers: Vec<u16> = vec![];
-        self.gen_numbers(&mut numbers, min_numbers);
+        let mut numbers: Vec<u64> = vec![];
+        self.gen_numbers(&mut numbers, min_numbers, rng);
 
-        let mut line = String::new();
+        let mut tokens = vec![];
+        let mut answers = vec![];
         // numbers中的插入gap的索引位置
         let mut number_pos: u16 = 0;
         let mut miss_numbers = min_numbers;
@@ -43,23 +60,21 @@ impl MissingNumberOpts {
         for gap in gaps {
             // 随机数的范围
             let upper_bound = numbers.len() as u16 - miss_numbers + 1;
-            let gap_start = rng().random_range(number_pos..upper_bound);
+            let gap_start = rng.random_range(number_pos..upper_bound);
 
             // 填入数字
             for i in number_pos..gap_start {
-                line.push_str(&numbers[i as usize].to_string());
-                line.push(' ');
+                tokens.push(Segment::Text(numbers[i as usize].to_string()));
             }
             // 填入missing
             for i in gap_start..gap_start + gap {
-                line.push_str(&"_".repeat(char_len(numbers[i as usize]) as usize));
-                line.push(' ');
+                tokens.push(Segment::Blank(char_len(numbers[i as usize])));
+                answers.push(numbers[i as usize].to_string());
             }
 
             // 填入gap的间隔数字
             if gap_start + gap < numbers.len() as u16 {
-                line.push_str(&numbers[(gap_start + gap) as usize].to_string());
-                line.push(' ');
+                tokens.push(Segment::Text(numbers[(gap_start + gap) as usize].to_string()));
             }
 
             number_pos = gap_start + gap + 1;
@@ -70,41 +85,41 @@ impl MissingNumberOpts {
         }
         // 填入剩余的number数字
         for i in number_pos..numbers.len() as u16 {
-            line.push_str(&numbers[i as usize].to_string());
-            line.push(' ');
+            tokens.push(Segment::Text(numbers[i as usize].to_string()));
         }
-        line.trim().to_string()
+        Problem::joined(tokens, answers)
     }
 
     // 根据行宽，确定数字序列
-    fn gen_numbers(&self, numbers: &mut Vec<u16>, min_numbers: u16) {
+    fn gen_numbers(&self, numbers: &mut Vec<u64>, min_numbers: u16, rng: &mut impl Rng) {
         // 从start开始截取不会超过line width的数字
-        let mut number = self.gen_num_start(min_numbers);
+        let mut number = self.gen_num_start(min_numbers, rng);
         let mut width = char_len(number);
-        let step = i16::unsigned_abs(self.step);
-        while width <= self.line_width {
+        let step = self.step.unsigned_abs();
+        let line_width = self.effective_line_width();
+        while width <= line_width {
             numbers.push(number);
-            number = if self.step > 0 {
-                number + step
-            } else {
-                if number < step {
-                    break;
-                }
-                number - step
-            };
+            // 超出 u64 的范围时结束
+            let next = if self.step > 0 { number.checked_add(step) } else { number.checked_sub(step) };
+            match next {
+                Some(next) => number = next,
+                None => break,
+            }
             width += 1 + char_len(number); //空格间隔
         }
     }
 
-    fn gen_num_start(&self, min_numbers: u16) -> u16 {
+    fn gen_num_start(&self, min_numbers: u16, rng: &mut impl Rng) -> u64 {
         let (mut lower_bound, mut upper_bound) = (self.number_min_inclusive, self.number_max_inclusive);
-        let step = i16::unsigned_abs(self.step);
+        let step = self.step.unsigned_abs();
+        let line_width = self.effective_line_width();
+        let min_span = (min_numbers as u64).saturating_mul(step);
         if self.step > 0 {
-            upper_bound = self.number_max_inclusive - min_numbers * step;
+            upper_bound = self.number_max_inclusive.saturating_sub(min_span);
 
             let mut number = self.number_max_inclusive;
             let mut width = char_len(number);
-            while width <= self.line_width {
+            while width <= line_width {
                 if number < step {
                     break;
                 }
@@ -112,32 +127,32 @@ impl MissingNumberOpts {
                 // 数字和间隔空格的宽度
                 width += char_len(number) + 1;
             }
-            number += step;
+            number = number.saturating_add(step);
             // 既满足step要求， 又满足line width的要求
             upper_bound = min(upper_bound, number);
             if upper_bound < self.number_min_inclusive {
                 panic!("Please shorten step or increase line width");
             }
         } else {
-            lower_bound = self.number_min_inclusive + min_numbers * step;
+            lower_bound = self.number_min_inclusive.saturating_add(min_span);
 
             let mut number = self.number_min_inclusive;
             let mut width = char_len(number);
-            while width <= self.line_width {
-                number += step;
+            while width <= line_width {
+                let Some(next) = number.checked_add(step) else { break };
+                number = next;
                 // 数字和间隔空格的宽度
                 width += char_len(number) + 1;
             }
-            number -= step;
+            number = number.saturating_sub(step);
             // 既满足step要求， 又满足line width的要求
             lower_bound = max(lower_bound, number);
 
             // 上限只是一个参考值，允许超过上限
         }
 
-        let mut rng = rand::rng();
-        upper_bound = if lower_bound == upper_bound { upper_bound + 1} else { upper_bound };
-        let die = Uniform::new(lower_bound, upper_bound).unwrap();
+        // 取 [lower_bound, upper_bound)，两者相等时取 lower_bound
+        let die = Uniform::new_inclusive(lower_bound, max(lower_bound, upper_bound.saturating_sub(1))).unwrap();
         // 从start开始满足gap要求，每个gap都至少间隔了一个数字，而且line width不会超过数字范围
         let mut start = rng.sample(die);
         if self.start_as_multiple_step {
@@ -147,10 +162,10 @@ impl MissingNumberOpts {
                 let mut times = start / step;
                 let r = start % step;
                 times = if r > step / 2 { times + 1 } else { times };
-                start = times * step;
+                start = times.saturating_mul(step);
                 // 如果超过下限，则直接向上
                 if start < self.number_min_inclusive {
-                    start = (times + 1) * step;
+                    start = times.saturating_add(1).saturating_mul(step);
                 }
             }
         }
@@ -158,8 +173,7 @@ impl MissingNumberOpts {
     }
 
     // 随机产生每个gap有多少个位置（数字）
-    fn gen_gaps(&self, gaps: &mut Vec<u16>) {
-        let mut rng = rng();
+    fn gen_gaps(&self, gaps: &mut Vec<u16>, rng: &mut impl Rng) {
         let die = Uniform::new(1, self.miss_max_per_gap + 1).unwrap();
         for _ in 0..self.gaps_per_line {
             gaps.push(rng.sample(die));
@@ -169,12 +183,62 @@ impl MissingNumberOpts {
     #[allow(dead_code)]
     fn gen_missing_numbers_to_txt(&self) {
         let mut lines = String::new();
+        let mut rng = new_rng(self.sheet.seed);
         for _i in 0..self.count {
-            lines.push_str(&self.gen_single_missing_numbers());
-            lines.push_str("\n");
+            lines.push_str(&render_text(&self.gen_single_missing_numbers(&mut rng), &self.layout.answer_style, false));
+            lines.push('\n');
         }
-        write(&lines.trim(), "./output/missing-numbers.txt").expect("Write error!");
+        write(lines.trim(), "./output/missing-numbers.txt").expect("Write error!");
         println!("Generate missing numbers successfully");
     }
 }
 
+#[cfg(test)]
+mod test {
+    use crate::{LayoutOpts, MissingNumberOpts, NumberStyle, SheetOpts};
+    use crate::utils::new_rng;
+
+    #[test]
+    fn test_gen_large_numbers() {
+        for step in [7, -7] {
+            let opts = MissingNumberOpts {
+                count: 5,
+                miss_max_per_gap: 2,
+                gaps_per_line: 2,
+                step,
+                start_as_multiple_step: false,
+                line_width: 60,
+                number_min_inclusive: 9_999_000,
+                number_max_inclusive: 10_000_000,
+                output_docx_font_size: 36,
+                label_max: None,
+                layout: LayoutOpts::default(),
+                sheet: SheetOpts::default(),
+            };
+            for problem in opts.gen_problems(&mut new_rng(Some(3))) {
+                let numbers: Vec<i64> = problem.solved_text().split_whitespace().map(|n| n.parse().unwrap()).collect();
+                assert!(numbers.len() >= 5);
+                assert!(numbers.windows(2).all(|w| w[1] - w[0] == step));
+            }
+        }
+    }
+
+    #[test]
+    fn test_effective_line_width() {
+        let opts = MissingNumberOpts {
+            count: 5,
+            miss_max_per_gap: 3,
+            gaps_per_line: 2,
+            step: 1,
+            line_width: 37,
+            number_max_inclusive: 100,
+            output_docx_font_size: 36,
+            layout: LayoutOpts { numbering: NumberStyle::Arabic, ..LayoutOpts::default() },
+            ..Default::default()
+        };
+        // "5." 和 "100." 以及之后的空格
+        assert_eq!(opts.effective_line_width(), 34);
+        let opts = MissingNumberOpts { label_max: Some(100), ..opts };
+        assert_eq!(opts.effective_line_width(), 32);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Currency;
+use crate::i18n::{t, tf};
+use crate::utils::group_digits;
+
+impl Currency {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::Cny => "¥",
+            Currency::Usd => "$",
+        }
+    }
+
+    // 出题时金额是最小的常用硬币的倍数(以分计)：人民币 1角，美元 1美分
+    pub fn coin(&self) -> i64 {
+        match self {
+            Currency::Cny => 10,
+            Currency::Usd => 1,
+        }
+    }
+
+    // 找零时付出的纸币(以分计)，两种货币恰好相同：1, 5, 10, 20, 50, 100
+    pub fn notes(&self) -> &'static [i64] {
+        match self {
+            Currency::Cny | Currency::Usd => &[100, 500, 1000, 2000, 5000, 10000],
+        }
+    }
+
+    // 以分计的金额，比如 123450 -> ¥1,234.50，千位分隔符和小数点来自消息文件
+    pub fn amount_text(&self, amount: i64) -> String {
+        let cents = amount.unsigned_abs();
+        let whole = group_digits(cents / 100, &t("money.thousands_separator"));
+        let sign = if amount < 0 { "-" } else { "" };
+        format!("{}{}{}{}{:02}", sign, self.symbol(), whole, t("money.decimal_separator"), cents % 100)
+    }
+
+    // 复名数的写法，比如 350 -> 3元5角，为 0 的单位省略，0 写成 0元
+    pub fn units_text(&self, amount: i64) -> String {
+        let cents = amount.unsigned_abs();
+        let parts: Vec<(&str, u64)> = match self {
+            Currency::Cny => vec![("money.yuan", cents / 100), ("money.jiao", cents / 10 % 10), ("money.fen", cents % 10)],
+            Currency::Usd => vec![("money.dollar", cents / 100), ("money.cent", cents % 100)],
+        };
+        let mut text: Vec<String> = parts.iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(key, n)| tf(key, &[("n", n)]))
+            .collect();
+        if text.is_empty() {
+            text.push(tf(parts[0].0, &[("n", &0)]));
+        }
+        let sign = if amount < 0 { "-" } else { "" };
+        format!("{}{}", sign, text.join(&t("money.units_separator")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Currency;
+
+    #[test]
+    fn test_amount_text() {
+        assert_eq!(Currency::Cny.amount_text(350), "¥3.50");
+        assert_eq!(Currency::Cny.amount_text(5), "¥0.05");
+        assert_eq!(Currency::Usd.amount_text(123456789), "$1,234,567.89");
+        assert_eq!(Currency::Usd.amount_text(-100000), "-$1,000.00");
+    }
+
+    #[test]
+    fn test_units_text() {
+        assert_eq!(Currency::Cny.units_text(350), "3元5角");
+        assert_eq!(Currency::Cny.units_text(1205), "12元5分");
+        assert_eq!(Currency::Cny.units_text(70), "7角");
+        assert_eq!(Currency::Cny.units_text(0), "0元");
+        assert_eq!(Currency::Usd.units_text(1250), "12美元50美分");
+    }
+}
@@ -0,0 +1,161 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::NumberBondsOpts;
+use crate::add_minus::preset_bonds;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 一个算式组中各个算式之间的分隔
+const EQUATION_SEPARATOR: &str = "    ";
+
+impl NumberBondsOpts {
+    pub fn gen_number_bonds_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/number-bonds.docx", Source::new("number-bonds", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        let families = self.category.starts_with('f');
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page.unwrap_or(if families { 1 } else { 4 }),
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        let c = &self.category;
+        self.gen_bonds(rng).into_iter().map(|(whole, l, r)| {
+            if c.starts_with('f') {
+                gen_family(whole, l, r, rng)
+            } else if c.starts_with('b') {
+                gen_bond(whole, l, r, false, rng)
+            } else if c.starts_with('w') {
+                gen_bond(whole, l, r, true, rng)
+            } else {
+                let missing_whole = rng.random_bool(1.0 / 3.0);
+                gen_bond(whole, l, r, missing_whole, rng)
+            }
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        if self.category.starts_with('f') {
+            t("heading.fact_families")
+        } else {
+            t("heading.number_bonds")
+        }
+    }
+
+    // 从 --presets 的分与合中抽取 count 个，全部用完之前不重复，两个部分随机交换位置
+    fn gen_bonds(&self, rng: &mut impl Rng) -> Vec<(i64, i64, i64)> {
+        let mut pool: Vec<(u32, u32, u32)> = self.presets.iter().flat_map(|p| preset_bonds(*p)).collect();
+        pool.sort();
+        pool.dedup();
+        let mut bonds = vec![];
+        while bonds.len() < self.count as usize {
+            pool.shuffle(rng);
+            bonds.extend(pool.iter().take(self.count as usize - bonds.len()).map(|&(whole, l, r)| {
+                let (l, r) = if rng.random_bool(0.5) { (l, r) } else { (r, l) };
+                (whole as i64, l as i64, r as i64)
+            }));
+        }
+        bonds
+    }
+}
+
+// 分与合，missing_whole 时求整体(合)，否则求其中一个部分(分)
+fn gen_bond(whole: i64, l: i64, r: i64, missing_whole: bool, rng: &mut impl Rng) -> Problem {
+    if missing_whole {
+        return Problem { segments: vec![Segment::Bond { whole: None, parts: [Some(l), Some(r)] }], answers: vec![whole.to_string()], fact: None }
+            .with_fact(l, Op::Add, r);
+    }
+    let (parts, answer, known) = if rng.random_bool(0.5) { ([None, Some(r)], l, r) } else { ([Some(l), None], r, l) };
+    Problem { segments: vec![Segment::Bond { whole: Some(whole), parts }], answers: vec![answer.to_string()], fact: None }
+        .with_fact(whole, Op::Minus, known)
+}
+
+// 一图四式：由三个数写出相关的加减法算式，每个算式随机空出一个数
+// 两个部分相同时只有两个算式
+fn gen_family(whole: i64, l: i64, r: i64, rng: &mut impl Rng) -> Problem {
+    let mut equations = vec![(l, Op::Add, r, whole), (r, Op::Add, l, whole), (whole, Op::Minus, l, r), (whole, Op::Minus, r, l)];
+    equations.dedup();
+    let mut segments = vec![Segment::Text(tf("bonds.family", &[("a", &l), ("b", &r), ("whole", &whole)]))];
+    let mut answers = vec![];
+    for (i, (a, op, b, c)) in equations.into_iter().enumerate() {
+        let blank = rng.random_range(0..3);
+        let terms = [a.to_string(), format!(" {} ", op.symbol()), b.to_string(), " = ".to_string(), c.to_string()];
+        let mut text = if i > 0 { EQUATION_SEPARATOR.to_string() } else { String::new() };
+        for (j, term) in terms.into_iter().enumerate() {
+            if j == blank * 2 {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+                segments.push(Segment::Blank(2));
+                answers.push(term);
+            } else {
+                text.push_str(&term);
+            }
+        }
+        segments.push(Segment::Text(text));
+    }
+    segments.retain(|s| s != &Segment::Text(String::new()));
+    Problem { segments, answers, fact: None }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{NumberBondsOpts, Preset};
+    use crate::add_minus::preset_bonds;
+    use crate::problem::{Op, Segment};
+    use crate::utils::new_rng;
+
+    #[test]
+    fn test_preset_bonds() {
+        for preset in [Preset::P1, Preset::P2, Preset::P3, Preset::P4] {
+            for (whole, l, r) in preset_bonds(preset) {
+                assert_eq!(whole, l + r);
+            }
+        }
+        assert!(preset_bonds(Preset::P2).contains(&(18, 9, 9)));
+        assert!(preset_bonds(Preset::P1).contains(&(18, 9, 9)));
+    }
+
+    #[test]
+    fn test_gen_bonds() {
+        let opts = NumberBondsOpts { count: 40, category: "x".to_string(), presets: vec![Preset::P1, Preset::P4], ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(7))) {
+            let Segment::Bond { whole, parts } = problem.segments[0] else { panic!("expect a bond") };
+            let answer: i64 = problem.answers[0].parse().unwrap();
+            let fact = problem.fact.unwrap();
+            match (whole, parts) {
+                (None, [Some(l), Some(r)]) => assert_eq!((l + r, fact.op), (answer, Op::Add)),
+                (Some(w), [None, Some(r)]) | (Some(w), [Some(r), None]) => assert_eq!((w - r, fact.op), (answer, Op::Minus)),
+                _ => panic!("expect exactly one blank: {:?}", problem),
+            }
+            assert!(answer <= 18);
+        }
+    }
+
+    #[test]
+    fn test_gen_families() {
+        let opts = NumberBondsOpts { count: 40, category: "f".to_string(), presets: vec![Preset::P1, Preset::P4], ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(8))) {
+            // 3、5、8：3 + 5 = 8    5 + 3 = 8    8 - 3 = 5    8 - 5 = 3
+            let text = problem.solved_text();
+            let (numbers, equations) = text.split_once('：').unwrap();
+            let numbers: Vec<i64> = numbers.split('、').map(|n| n.parse().unwrap()).collect();
+            let equations: Vec<&str> = equations.split("    ").collect();
+            assert_eq!(equations.len(), if numbers[0] == numbers[1] { 2 } else { 4 });
+            assert_eq!(problem.answers.len(), equations.len());
+            for equation in equations {
+                let tokens: Vec<i64> = equation.split([' ', '+', '-', '=']).filter(|s| !s.is_empty()).map(|n| n.parse().unwrap()).collect();
+                let expected = if equation.contains('+') { tokens[0] + tokens[1] } else { tokens[0] - tokens[1] };
+                assert_eq!(expected, tokens[2], "{}", text);
+            }
+        }
+    }
+}
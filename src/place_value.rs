@@ -0,0 +1,246 @@
+use rand::Rng;
+use rand::seq::index::sample;
+use crate::PlaceValueOpts;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Problem, Segment};
+use crate::utils::{char_len, digit_at, group_digits};
+
+// 从个位起各个数位的计数单位的消息键：1个时的写法，以及其他个数的写法
+const PLACES: [(&str, &str); 9] = [
+    ("place_value.one", "place_value.ones"),
+    ("place_value.ten", "place_value.tens"),
+    ("place_value.hundred", "place_value.hundreds"),
+    ("place_value.thousand", "place_value.thousands"),
+    ("place_value.ten_thousand", "place_value.ten_thousands"),
+    ("place_value.hundred_thousand", "place_value.hundred_thousands"),
+    ("place_value.million", "place_value.millions"),
+    ("place_value.ten_million", "place_value.ten_millions"),
+    ("place_value.hundred_million", "place_value.hundred_millions"),
+];
+
+pub const MAX_DIGITS: u32 = PLACES.len() as u32;
+
+// 第 position 位上有 n 个计数单位，比如 4个千、1 ten；None 时是空白之后的计数单位，比如 __ tens
+fn place_text(position: u32, n: Option<u64>) -> String {
+    let (one, other) = PLACES[position as usize];
+    match n {
+        Some(1) => tf(one, &[("n", &1)]),
+        Some(n) => tf(other, &[("n", &n)]),
+        None => tf(other, &[("n", &"")]),
+    }
+}
+
+// 题目中的数按消息文件中的分隔符三位一组，比如 4,372
+fn number_text(number: u64) -> String {
+    group_digits(number, &t("place_value.separator"))
+}
+
+// 相邻的文本合并为一个片段
+fn push_text(segments: &mut Vec<Segment>, text: &str) {
+    match segments.last_mut() {
+        Some(Segment::Text(s)) => s.push_str(text),
+        _ => segments.push(Segment::Text(text.to_string())),
+    }
+}
+
+impl PlaceValueOpts {
+    pub fn gen_place_value_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/place-value.docx", Source::new("place-value", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        (0..self.count).map(|_| {
+            let c = &self.category;
+            if c.starts_with('d') {
+                self.gen_decomposition(rng)
+            } else if c.starts_with('e') {
+                self.gen_expanded(rng)
+            } else if c.starts_with('v') {
+                self.gen_digit_value(rng)
+            } else {
+                match rng.random_range(0..3) {
+                    0 => self.gen_decomposition(rng),
+                    1 => self.gen_expanded(rng),
+                    _ => self.gen_digit_value(rng),
+                }
+            }
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.place_value")
+    }
+
+    // digits 位的数，--zeros 时中间至少有一个 0，比如 4072
+    fn random_number(&self, rng: &mut impl Rng) -> u64 {
+        let mut digits: Vec<u64> = (0..self.digits).map(|i| rng.random_range(if i == 0 { 1..=9 } else { 0..=9 })).collect();
+        if self.zeros && self.digits >= 3 {
+            digits[rng.random_range(1..self.digits - 1) as usize] = 0;
+        }
+        digits.iter().fold(0, |number, d| number * 10 + d)
+    }
+
+    // 数位从高到低
+    fn positions(&self) -> impl Iterator<Item = u32> {
+        (0..self.digits).rev()
+    }
+
+    // 4,372 = __个千 + 3个百 + __个十 + 2个一，空白的数位随机选取；或者由计数单位组成数：__ = 4个千 + 3个百 + 7个十 + 2个一
+    fn gen_decomposition(&self, rng: &mut impl Rng) -> Problem {
+        let number = self.random_number(rng);
+        let compose = rng.random_bool(0.25);
+        let blanks = random_blanks(self.digits as usize, rng);
+        let mut segments = vec![];
+        let mut answers = vec![];
+        if compose {
+            segments.push(Segment::Blank(char_len(number)));
+            answers.push(number.to_string());
+        } else {
+            push_text(&mut segments, &number_text(number));
+        }
+        push_text(&mut segments, " = ");
+        for (i, position) in self.positions().enumerate() {
+            if i > 0 {
+                push_text(&mut segments, " + ");
+            }
+            let digit = digit_at(number, position);
+            if !compose && blanks.contains(&i) {
+                segments.push(Segment::Blank(1));
+                answers.push(digit.to_string());
+                push_text(&mut segments, &place_text(position, None));
+            } else {
+                push_text(&mut segments, &place_text(position, Some(digit)));
+            }
+        }
+        Problem { segments, answers, fact: None }
+    }
+
+    // 4,072 = 4000 + __ + 2，为 0 的数位不写；或者 __ = 4000 + 70 + 2
+    fn gen_expanded(&self, rng: &mut impl Rng) -> Problem {
+        let number = self.random_number(rng);
+        let terms: Vec<u64> = self.positions()
+            .map(|position| digit_at(number, position) * 10u64.pow(position))
+            .filter(|term| *term != 0)
+            .collect();
+        let compose = rng.random_bool(0.25);
+        let blanks = random_blanks(terms.len(), rng);
+        let mut segments = vec![];
+        let mut answers = vec![];
+        if compose {
+            segments.push(Segment::Blank(char_len(number)));
+            answers.push(number.to_string());
+        } else {
+            push_text(&mut segments, &number_text(number));
+        }
+        push_text(&mut segments, " = ");
+        for (i, term) in terms.iter().enumerate() {
+            if i > 0 {
+                push_text(&mut segments, " + ");
+            }
+            if !compose && blanks.contains(&i) {
+                segments.push(Segment::Blank(char_len(*term)));
+                answers.push(term.to_string());
+            } else {
+                push_text(&mut segments, &term.to_string());
+            }
+        }
+        Problem { segments, answers, fact: None }
+    }
+
+    // 4,372 中的 7 表示 __，所问的数字不为 0，且在数中只出现一次
+    fn gen_digit_value(&self, rng: &mut impl Rng) -> Problem {
+        loop {
+            let number = self.random_number(rng);
+            let unique: Vec<u32> = (0..self.digits)
+                .filter(|p| {
+                    let digit = digit_at(number, *p);
+                    digit != 0 && (0..self.digits).filter(|q| digit_at(number, *q) == digit).count() == 1
+                })
+                .collect();
+            if unique.is_empty() {
+                continue;
+            }
+            let position = unique[rng.random_range(0..unique.len())];
+            let digit = digit_at(number, position);
+            let value = digit * 10u64.pow(position);
+            let question = tf("place_value.digit_value", &[("number", &number_text(number)), ("digit", &digit)]);
+            return Problem { segments: vec![Segment::Text(question + " "), Segment::Blank(char_len(value))], answers: vec![value.to_string()], fact: None };
+        }
+    }
+}
+
+// n 项中随机选取至少一项作为空白
+fn random_blanks(n: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let count = rng.random_range(1..=n);
+    sample(rng, n, count).into_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PlaceValueOpts;
+    use crate::place_value::place_text;
+    use crate::utils::{digit_at, new_rng};
+
+    // "4,372" -> 4372, "4个千" -> 4000
+    fn value(text: &str) -> u64 {
+        let text = text.trim().replace(',', "");
+        match text.split_once('个') {
+            Some((n, unit)) => {
+                let place = ["一", "十", "百", "千", "万"].iter().position(|u| *u == unit).unwrap();
+                n.parse::<u64>().unwrap() * 10u64.pow(place as u32)
+            }
+            None => text.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_place_text() {
+        assert_eq!(place_text(3, Some(4)), "4个千");
+        assert_eq!(place_text(0, None), "个一");
+        assert_eq!(place_text(4, Some(1)), "1个万");
+    }
+
+    #[test]
+    fn test_gen_decomposition() {
+        for category in ["d", "e"] {
+            let opts = PlaceValueOpts { count: 40, category: category.to_string(), digits: 4, zeros: true, ..Default::default() };
+            for problem in opts.gen_problems(&mut new_rng(Some(9))) {
+                // 4,072 = 4个千 + 0个百 + 7个十 + 2个一 或 4,072 = 4000 + 70 + 2
+                let text = problem.solved_text();
+                let (number, terms) = text.split_once(" = ").unwrap();
+                let number = value(number);
+                assert_eq!(terms.split(" + ").map(value).sum::<u64>(), number, "{}", text);
+                assert!((1000..10000).contains(&number) && (digit_at(number, 1) == 0 || digit_at(number, 2) == 0));
+                assert!(!problem.answers.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_digit_value() {
+        let opts = PlaceValueOpts { count: 40, category: "v".to_string(), digits: 6, ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(10))) {
+            // 604,372 中的 7 表示 70
+            let text = problem.solved_text();
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            let (number, digit, answer) = (tokens[0].replace(',', ""), tokens[2], tokens[4]);
+            let position = number.len() - 1 - number.find(digit).unwrap();
+            assert_eq!(number.matches(digit).count(), 1);
+            assert_eq!(answer, format!("{}{}", digit, "0".repeat(position)));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+
+// 运算符
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Op {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Minus,
+    #[serde(rename = "*")]
+    Mul,
+    #[serde(rename = "/")]
+    Div,
+}
+
+impl Op {
+    pub fn symbol(&self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Minus => '-',
+            Op::Mul => '×',
+            Op::Div => '÷',
+        }
+    }
+
+    // 同时接受 × ÷ 和 * /
+    pub fn from_symbol(s: &str) -> Option<Op> {
+        match s {
+            "+" => Some(Op::Add),
+            "-" => Some(Op::Minus),
+            "×" | "*" => Some(Op::Mul),
+            "÷" | "/" => Some(Op::Div),
+            _ => None,
+        }
+    }
+}
+
+// 算式背后的数学事实，比如 9 + 7，用于记录和统计练习情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Fact {
+    pub left: i64,
+    pub op: Op,
+    pub right: i64,
+}
+
+// 题目片段：普通文本、分数、钟面、数的分与合、乘法表方格、竖式或者需要作答的空白
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Segment {
+    Text(String),
+    // 分数或带分数，比如 3/4, 1 1/2，docx 中分子分母上下排列
+    Fraction(String),
+    // 一天中第 minutes 分钟的钟面，hands 为 false 时是没有指针的空白钟面，答案页中总是画出指针
+    // 文本形式中没有钟面
+    Clock { minutes: u16, hands: bool },
+    // 数的分与合，上面是整体 whole，下面是两个部分 parts，None 是需要作答的空白，与 Blank 一样按顺序对应答案
+    // 文本形式写成 8 = 3 + 5
+    Bond { whole: Option<i64>, parts: [Option<i64>; 2] },
+    // 乘法表方格，表头第一行是 columns，第一列是 rows，cells[i][j] 是 rows[i] × columns[j]
+    // None 是需要作答的空白，按行的顺序对应答案；文本形式每行一行，各列右对齐
+    Grid { rows: Vec<i64>, columns: Vec<i64>, cells: Vec<Vec<Option<i64>>> },
+    // 乘法或除法竖式，每个数字占一格，题目中只写出运算数并留出演算的空间，答案页写出每一步
+    // 对应一个答案；文本形式写成 34 × 27 = 918
+    Written { op: Op, left: i64, right: i64 },
+    // 空白的默认宽度(字符数)，0 表示不预留空白，比如加减法末尾的 =
+    Blank(u16),
+}
+
+// 一道题目及其答案， answers 与 segments 中的 Blank 按顺序一一对应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Problem {
+    pub segments: Vec<Segment>,
+    pub answers: Vec<String>,
+    // 由两个数运算得到的题目，记录其运算数和运算符
+    pub fact: Option<Fact>,
+}
+
+impl Problem {
+    // 形如 "3 + 4 =" 的算式，答案写在末尾
+    pub fn expr(question: String, answer: String) -> Problem {
+        Problem { segments: vec![Segment::Text(question + " "), Segment::Blank(0)], answers: vec![answer], fact: None }
+    }
+
+    pub fn with_fact(mut self, left: i64, op: Op, right: i64) -> Problem {
+        self.fact = Some(Fact { left, op, right });
+        self
+    }
+
+    // 以空格连接各个片段，相邻的文本合并为一个片段
+    pub fn joined(tokens: Vec<Segment>, answers: Vec<String>) -> Problem {
+        let mut segments = vec![];
+        let mut text = String::new();
+        for (i, token) in tokens.into_iter().enumerate() {
+            if i > 0 {
+                text.push(' ');
+            }
+            match token {
+                Segment::Text(s) => text.push_str(&s),
+                blank => {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(blank);
+                }
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Problem { segments, answers, fact: None }
+    }
+
+    // 空白处填入答案后的完整文本，用于答案页
+    pub fn solved_text(&self) -> String {
+        self.filled_text(&self.answers)
+    }
+
+    // 空白处依次填入给定的内容，不足的留空
+    pub fn filled_text(&self, answers: &[String]) -> String {
+        let mut answers = answers.iter();
+        let mut text = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(s) | Segment::Fraction(s) => text.push_str(s),
+                Segment::Blank(_) => text.push_str(answers.next().map(|a| a.as_str()).unwrap_or("")),
+                Segment::Clock { .. } => {}
+                Segment::Bond { whole, parts } => {
+                    let [whole, l, r] = bond_texts(whole, parts, || answers.next().cloned().unwrap_or_default());
+                    text.push_str(&format!("{} = {} + {}", whole, l, r));
+                }
+                Segment::Grid { rows, columns, cells } => {
+                    text.push_str(&grid_text(&grid_texts(rows, columns, cells, || answers.next().cloned().unwrap_or_default())));
+                }
+                Segment::Written { op, left, right } => {
+                    text.push_str(&written_text(*op, *left, *right, answers.next().map(|a| a.as_str()).unwrap_or("")));
+                }
+            }
+        }
+        text
+    }
+
+    // 作答是否正确，只有一个空白时允许答案中有空格，比如带分数 1 1/2 被拆成了 ["1", "1/2"]
+    pub fn is_correct(&self, given: &[String]) -> bool {
+        let all_same = given.len() == self.answers.len() && given.iter().zip(&self.answers).all(|(g, a)| same_answer(g, a));
+        all_same || (self.answers.len() == 1 && given.len() > 1 && same_answer(&given.join(" "), &self.answers[0]))
+    }
+
+    // 只有文本和空白，可以写成一段文字
+    pub fn is_plain(&self) -> bool {
+        self.segments.iter().all(|s| matches!(s, Segment::Text(_) | Segment::Blank(_)))
+    }
+
+    // 含有钟面，只能画在文档中
+    pub fn has_clock(&self) -> bool {
+        self.segments.iter().any(|s| matches!(s, Segment::Clock { .. }))
+    }
+
+    // 含有分与合、方格或竖式，需要写成表格
+    pub fn has_table(&self) -> bool {
+        self.segments.iter().any(|s| matches!(s, Segment::Bond { .. } | Segment::Grid { .. } | Segment::Written { .. }))
+    }
+}
+
+// 数字按数值比较，比如 07 与 7、0.50 与 0.5 相同，其余的按原文比较
+fn same_answer(given: &str, answer: &str) -> bool {
+    if let (Ok(g), Ok(a)) = (given.parse::<i64>(), answer.parse::<i64>()) {
+        return g == a;
+    }
+    let decimal = |s: &str| s.bytes().all(|b| b.is_ascii_digit() || b == b'-' || b == b'.').then(|| s.parse::<f64>().ok()).flatten();
+    match (decimal(given), decimal(answer)) {
+        (Some(g), Some(a)) => g == a,
+        _ => given == answer,
+    }
+}
+
+// 分与合的整体和两个部分，空白处依次取 blank() 的结果
+pub fn bond_texts(whole: &Option<i64>, parts: &[Option<i64>; 2], mut blank: impl FnMut() -> String) -> [String; 3] {
+    [whole, &parts[0], &parts[1]].map(|n| match n {
+        Some(n) => n.to_string(),
+        None => blank(),
+    })
+}
+
+// 方格各行的文本，第一行是表头，左上角是 ×，空白处依次取 blank() 的结果
+pub fn grid_texts(rows: &[i64], columns: &[i64], cells: &[Vec<Option<i64>>], mut blank: impl FnMut() -> String) -> Vec<Vec<String>> {
+    let header = std::iter::once(Op::Mul.symbol().to_string()).chain(columns.iter().map(|c| c.to_string())).collect();
+    let mut texts = vec![header];
+    for (row, cells) in rows.iter().zip(cells) {
+        let cells = cells.iter().map(|n| match n {
+            Some(n) => n.to_string(),
+            None => blank(),
+        });
+        texts.push(std::iter::once(row.to_string()).chain(cells).collect());
+    }
+    texts
+}
+
+// 方格的文本形式，各列按最宽的单元格右对齐
+pub fn grid_text(texts: &[Vec<String>]) -> String {
+    let columns = texts.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|j| texts.iter().filter_map(|row| row.get(j)).map(|s| s.chars().count()).max().unwrap_or(0))
+        .collect();
+    let lines: Vec<String> = texts.iter()
+        .map(|row| row.iter().zip(&widths).map(|(s, w)| format!("{:>w$}", s, w = w)).collect::<Vec<_>>().join(" "))
+        .collect();
+    lines.join("\n")
+}
+
+// 竖式的文本形式
+pub fn written_text(op: Op, left: i64, right: i64, answer: &str) -> String {
+    format!("{} {} {} = {}", left, op.symbol(), right, answer)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::problem::{Problem, Segment};
+
+    #[test]
+    fn test_solved_text() {
+        let p = Problem::expr("3 + 4 =".to_string(), "7".to_string());
+        assert_eq!(p.solved_text(), "3 + 4 = 7");
+
+        let p = Problem {
+            segments: vec![Segment::Text("1 ".to_string()), Segment::Blank(1), Segment::Text(" 3".to_string())],
+            answers: vec!["2".to_string()],
+            fact: None,
+        };
+        assert_eq!(p.solved_text(), "1 2 3");
+    }
+
+    #[test]
+    fn test_joined() {
+        let p = Problem::joined(
+            vec![Segment::Text("1".to_string()), Segment::Blank(1), Segment::Blank(1), Segment::Text("4".to_string()), Segment::Text("5".to_string())],
+            vec!["2".to_string(), "3".to_string()]);
+        assert_eq!(p.segments, vec![Segment::Text("1 ".to_string()), Segment::Blank(1),
+                                    Segment::Text(" ".to_string()), Segment::Blank(1), Segment::Text(" 4 5".to_string())]);
+        assert_eq!(p.solved_text(), "1 2 3 4 5");
+    }
+
+    #[test]
+    fn test_is_correct() {
+        let answer = |s: &str| s.split(' ').map(|a| a.to_string()).collect::<Vec<_>>();
+        let p = Problem::expr("1/2 + 1 =".to_string(), "1 1/2".to_string());
+        assert!(p.is_correct(&answer("1 1/2")));
+        assert!(!p.is_correct(&answer("3/2")));
+        let p = Problem::joined(vec![Segment::Blank(1), Segment::Blank(1)], vec!["2".to_string(), "3".to_string()]);
+        assert!(p.is_correct(&answer("2 3")));
+        assert!(!p.is_correct(&answer("23")));
+        assert!(!p.is_correct(&answer("2")));
+        // 数字按数值比较
+        let p = Problem::expr("3 + 4 =".to_string(), "7".to_string());
+        assert!(p.is_correct(&answer("07")));
+        assert!(p.is_correct(&answer("+7")));
+        assert!(!p.is_correct(&answer("70")));
+        let p = Problem::expr("0.2 + 0.3 =".to_string(), "0.5".to_string());
+        assert!(p.is_correct(&answer("0.50")));
+        assert!(!p.is_correct(&answer("0.05")));
+        let p = Problem::expr("x =".to_string(), i64::MAX.to_string());
+        assert!(!p.is_correct(&answer(&(i64::MAX - 1).to_string())));
+    }
+
+    #[test]
+    fn test_bond_text() {
+        let p = Problem { segments: vec![Segment::Bond { whole: Some(8), parts: [None, Some(5)] }], answers: vec!["3".to_string()], fact: None };
+        assert_eq!(p.solved_text(), "8 = 3 + 5");
+        assert_eq!(p.filled_text(&[]), "8 =  + 5");
+        assert!(!p.is_plain() && p.has_table());
+    }
+
+    #[test]
+    fn test_grid_text() {
+        let p = Problem {
+            segments: vec![Segment::Grid { rows: vec![3, 12], columns: vec![4, 5], cells: vec![vec![Some(12), None], vec![None, Some(60)]] }],
+            answers: vec!["15".to_string(), "48".to_string()],
+            fact: None,
+        };
+        assert_eq!(p.solved_text(), " ×  4  5\n 3 12 15\n12 48 60");
+        assert_eq!(p.filled_text(&[]), " ×  4  5\n 3 12   \n12    60");
+        assert!(p.has_table());
+    }
+}
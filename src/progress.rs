@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::i18n::{t, tf};
+use crate::{ExportFormat, ProgressCommand, ProgressExportOpts, ProgressOpts, ProgressShowOpts};
+use crate::err::Error;
+use crate::problem::{Fact, Op};
+use crate::quiz::{prompt_text, Attempt};
+use crate::utils::write;
+
+// 一次作答记录
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    // 作答时间，unix 时间戳（秒）
+    pub timestamp: u64,
+    pub question: String,
+    pub answers: Vec<String>,
+    pub given: Vec<String>,
+    pub correct: bool,
+    pub elapsed_ms: u64,
+    pub fact: Option<Fact>,
+}
+
+impl Record {
+    pub fn from_attempt(attempt: &Attempt, timestamp: u64) -> Record {
+        Record {
+            timestamp,
+            question: prompt_text(&attempt.problem),
+            answers: attempt.problem.answers.clone(),
+            given: attempt.given.clone(),
+            correct: attempt.correct,
+            elapsed_ms: attempt.elapsed.as_millis() as u64,
+            fact: attempt.problem.fact,
+        }
+    }
+}
+
+// 按学生存放的练习记录，每个学生一个 JSON Lines 文件，每行一条记录
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(data_dir: &Path) -> Store {
+        Store { dir: data_dir.join("progress") }
+    }
+
+    fn path(&self, student: &str) -> PathBuf {
+        student_path(&self.dir, student, "jsonl")
+    }
+
+    // 没有记录的学生返回空列表
+    pub fn load(&self, student: &str) -> Result<Vec<Record>, Error> {
+        let path = self.path(student);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(path)?;
+        let mut records = vec![];
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            records.push(serde_json::from_str(line)?);
+        }
+        Ok(records)
+    }
+
+    pub fn append(&self, student: &str, records: &[Record]) -> Result<(), Error> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.path(student))?;
+        for record in records {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+        Ok(())
+    }
+}
+
+// 读取作答结果文件，按扩展名区分：
+// .jsonl: 练习记录文件，每行一条记录
+// .json: 记录数组，比如 progress export --raw --format json 的输出
+// .csv: 首行为列名，至少包括 left,op,right,correct，可选 elapsed_ms，比如 progress export --raw 的输出
+pub fn load_records(path: &Path) -> Result<Vec<Record>, Error> {
+    let content = fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("jsonl") => content.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| Ok(serde_json::from_str(l)?))
+            .collect(),
+        Some("json") => Ok(serde_json::from_str(&content)?),
+        _ => records_from_csv(&content),
+    }
+}
+
+fn records_from_csv(content: &str) -> Result<Vec<Record>, Error> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let header = split_csv_line(lines.next().unwrap_or(""));
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let (Some(left), Some(op), Some(right), Some(correct)) = (column("left"), column("op"), column("right"), column("correct")) else {
+        return Err(Error::Parse(t("error.csv_columns")));
+    };
+    let elapsed = column("elapsed_ms");
+
+    let mut records = vec![];
+    for (i, line) in lines.enumerate() {
+        let fields = split_csv_line(line);
+        let field = |c: usize| fields.get(c).map(|f| f.trim()).unwrap_or("");
+        let invalid = || Error::Parse(tf("error.csv_row", &[("row", &(i + 2)), ("line", &line)]));
+        // 没有运算数的题目（比如补充数字）跳过
+        if field(left).is_empty() {
+            continue;
+        }
+        let fact = Fact {
+            left: field(left).parse().map_err(|_| invalid())?,
+            op: Op::from_symbol(field(op)).ok_or_else(invalid)?,
+            right: field(right).parse().map_err(|_| invalid())?,
+        };
+        records.push(Record {
+            timestamp: 0,
+            question: String::new(),
+            answers: vec![],
+            given: vec![],
+            correct: matches!(field(correct), "true" | "1" | "yes"),
+            elapsed_ms: elapsed.and_then(|c| field(c).parse().ok()).unwrap_or(0),
+            fact: Some(fact),
+        });
+    }
+    Ok(records)
+}
+
+// 按逗号分割，支持双引号包含的字段
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// 学生名作为文件名，路径分隔符等特殊字符转义为 %XX，不同的学生名不会对应同一个文件
+pub fn student_path(dir: &Path, student: &str, extension: &str) -> PathBuf {
+    let mut name = String::new();
+    for c in student.chars() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            name.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                name.push_str(&format!("%{:02X}", b));
+            }
+        }
+    }
+    dir.join(format!("{}.{}", name, extension))
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// 事实所属的题型，一道题可以属于多个题型，比如 9 + 7 属于 加9、加7 和 进位加法
+pub fn fact_families(fact: &Fact) -> Vec<String> {
+    let mut families = vec![];
+    let (l, r) = (fact.left, fact.right);
+    // 有负数参与的加减法单独作为一类，不区分进位退位
+    let negative = match fact.op {
+        Op::Add => l < 0 || r < 0 || l.checked_add(r).is_none_or(|ans| ans < 0),
+        Op::Minus => l < 0 || r < 0 || l.checked_sub(r).is_none_or(|ans| ans < 0),
+        _ => false,
+    };
+    if negative {
+        return vec![t("progress.negative")];
+    }
+    match fact.op {
+        Op::Add => {
+            for n in [l, r] {
+                let family = tf("progress.add_facts", &[("n", &n)]);
+                if (0..=10).contains(&n) && !families.contains(&family) {
+                    families.push(family);
+                }
+            }
+            if l == r {
+                families.push(t("progress.doubles"));
+            }
+            if l % 10 + r % 10 >= 10 {
+                families.push(t("progress.carry"));
+            }
+        }
+        Op::Minus => {
+            if (0..=10).contains(&r) {
+                families.push(tf("progress.minus_facts", &[("n", &r)]));
+            }
+            if l % 10 < r % 10 {
+                families.push(t("progress.borrow"));
+            }
+        }
+        Op::Mul => {
+            for n in [l, r] {
+                let family = tf("progress.mul_facts", &[("n", &n)]);
+                if (0..=12).contains(&n) && !families.contains(&family) {
+                    families.push(family);
+                }
+            }
+        }
+        Op::Div => {
+            if (1..=12).contains(&r) {
+                families.push(tf("progress.div_facts", &[("n", &r)]));
+            }
+        }
+    }
+    families
+}
+
+// 一个题型的统计
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FamilyStats {
+    pub family: String,
+    pub attempts: usize,
+    pub correct: usize,
+    pub total_ms: u64,
+}
+
+impl FamilyStats {
+    pub fn accuracy(&self) -> f64 {
+        if self.attempts == 0 { 0.0 } else { self.correct as f64 / self.attempts as f64 }
+    }
+
+    pub fn avg_secs(&self) -> f64 {
+        if self.attempts == 0 { 0.0 } else { self.total_ms as f64 / 1000.0 / self.attempts as f64 }
+    }
+}
+
+// 按题型统计，正确率低的排在前面；没有运算数的题目（比如补充数字）不参与统计
+pub fn summarize(records: &[Record]) -> Vec<FamilyStats> {
+    let mut map: BTreeMap<String, FamilyStats> = BTreeMap::new();
+    for record in records {
+        let Some(fact) = &record.fact else { continue };
+        for family in fact_families(fact) {
+            let stats = map.entry(family.clone())
+                .or_insert(FamilyStats { family, attempts: 0, correct: 0, total_ms: 0 });
+            stats.attempts += 1;
+            stats.correct += record.correct as usize;
+            stats.total_ms += record.elapsed_ms;
+        }
+    }
+    let mut stats: Vec<FamilyStats> = map.into_values().collect();
+    stats.sort_by(|a, b| a.accuracy().total_cmp(&b.accuracy()));
+    stats
+}
+
+impl ProgressOpts {
+    pub fn run(&self) -> Result<(), Error> {
+        match &self.command {
+            ProgressCommand::Show(opts) => opts.show(),
+            ProgressCommand::Export(opts) => opts.export(),
+        }
+    }
+}
+
+impl ProgressShowOpts {
+    fn show(&self) -> Result<(), Error> {
+        let records = Store::new(&self.store.data_dir).load(&self.student)?;
+        let correct = records.iter().filter(|r| r.correct).count();
+        let accuracy = if records.is_empty() { 0.0 } else { correct as f64 * 100.0 / records.len() as f64 };
+        println!("{}", tf("progress.summary", &[("student", &self.student), ("attempts", &records.len()), ("percent", &format!("{:.1}", accuracy))]));
+        let headers = ["progress.attempts", "progress.correct", "progress.accuracy", "progress.avg_time"].map(t);
+        println!("{:<20}{:>10}{:>10}{:>10}{:>10}", t("progress.family"), headers[0], headers[1], headers[2], headers[3]);
+        for s in summarize(&records) {
+            println!("{:<20}{:>10}{:>10}{:>9.1}%{:>9.1}s", s.family, s.attempts, s.correct, s.accuracy() * 100.0, s.avg_secs());
+        }
+        Ok(())
+    }
+}
+
+impl ProgressExportOpts {
+    fn export(&self) -> Result<(), Error> {
+        let records = Store::new(&self.store.data_dir).load(&self.student)?;
+        let content = match (self.format, self.raw) {
+            (ExportFormat::Json, true) => serde_json::to_string_pretty(&records)?,
+            (ExportFormat::Json, false) => serde_json::to_string_pretty(&summarize(&records))?,
+            (ExportFormat::Csv, true) => records_to_csv(&records),
+            (ExportFormat::Csv, false) => stats_to_csv(&summarize(&records)),
+        };
+        match &self.output {
+            Some(path) => write(&content, &path.to_string_lossy())?,
+            None => println!("{}", content),
+        }
+        Ok(())
+    }
+}
+
+fn stats_to_csv(stats: &[FamilyStats]) -> String {
+    let mut lines = vec!["family,attempts,correct,accuracy,avg_secs".to_string()];
+    for s in stats {
+        lines.push(format!("{},{},{},{:.3},{:.2}", csv_field(&s.family), s.attempts, s.correct, s.accuracy(), s.avg_secs()));
+    }
+    lines.join("\n")
+}
+
+pub fn records_to_csv(records: &[Record]) -> String {
+    let mut lines = vec!["timestamp,question,answers,given,correct,elapsed_ms,left,op,right".to_string()];
+    for r in records {
+        let (left, op, right) = match &r.fact {
+            Some(f) => (f.left.to_string(), f.op.symbol().to_string(), f.right.to_string()),
+            None => (String::new(), String::new(), String::new()),
+        };
+        lines.push(format!("{},{},{},{},{},{},{},{},{}", r.timestamp, csv_field(&r.question),
+                           csv_field(&r.answers.join(" ")), csv_field(&r.given.join(" ")), r.correct, r.elapsed_ms, left, op, right));
+    }
+    lines.join("\n")
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::time::Duration;
+    use crate::problem::{Fact, Op, Problem};
+    use crate::progress::{csv_field, fact_families, records_from_csv, records_to_csv, split_csv_line, stats_to_csv, student_path, summarize, Record, Store};
+    use crate::quiz::Attempt;
+
+    fn record(left: i64, op: Op, right: i64, correct: bool, elapsed_ms: u64) -> Record {
+        Record {
+            timestamp: 0,
+            question: String::new(),
+            answers: vec![],
+            given: vec![],
+            correct,
+            elapsed_ms,
+            fact: Some(Fact { left, op, right }),
+        }
+    }
+
+    #[test]
+    fn test_fact_families() {
+        assert_eq!(fact_families(&Fact { left: 9, op: Op::Add, right: 7 }), vec!["加9", "加7", "进位加法"]);
+        assert_eq!(fact_families(&Fact { left: 6, op: Op::Add, right: 6 }), vec!["加6", "两数相同的加法", "进位加法"]);
+        assert_eq!(fact_families(&Fact { left: 23, op: Op::Add, right: 15 }), Vec::<String>::new());
+        assert_eq!(fact_families(&Fact { left: 13, op: Op::Minus, right: 7 }), vec!["减7", "退位减法"]);
+        assert_eq!(fact_families(&Fact { left: 45, op: Op::Minus, right: 23 }), Vec::<String>::new());
+        assert_eq!(fact_families(&Fact { left: -7, op: Op::Minus, right: -3 }), vec!["负数加减法"]);
+        assert_eq!(fact_families(&Fact { left: 3, op: Op::Minus, right: 7 }), vec!["负数加减法"]);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let records = vec![
+            record(9, Op::Add, 2, true, 1000),
+            record(9, Op::Add, 3, false, 3000),
+            record(5, Op::Add, 4, true, 2000),
+        ];
+        let stats = summarize(&records);
+        // 正确率低的排在前面
+        assert_eq!(stats[0].family, "加3");
+        assert_eq!(stats[0].accuracy(), 0.0);
+        let nine = stats.iter().find(|s| s.family == "加9").unwrap();
+        assert_eq!((nine.attempts, nine.correct), (2, 1));
+        assert_eq!(nine.avg_secs(), 2.0);
+        assert!(stats.iter().any(|s| s.family == "加5" && s.accuracy() == 1.0));
+        assert!(stats_to_csv(&stats).starts_with("family,attempts,correct,accuracy,avg_secs\n"));
+    }
+
+    #[test]
+    fn test_store() {
+        let dir = std::env::temp_dir().join(format!("gen_arithmatic_store_{}", std::process::id()));
+        let store = Store::new(&dir);
+        assert!(store.load("小明").unwrap().is_empty());
+
+        let attempt = Attempt {
+            problem: Problem::expr("9 + 7 =".to_string(), "16".to_string()).with_fact(9, Op::Add, 7),
+            given: vec!["15".to_string()],
+            correct: false,
+            elapsed: Duration::from_millis(2500),
+        };
+        let r = Record::from_attempt(&attempt, 100);
+        assert_eq!(r.question, "9 + 7 = ?");
+        store.append("小明", std::slice::from_ref(&r)).unwrap();
+        store.append("小明", std::slice::from_ref(&r)).unwrap();
+        assert_eq!(store.load("小明").unwrap(), vec![r.clone(), r]);
+        assert!(store.load("../小明").unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_student_path() {
+        let dir = Path::new("data");
+        assert_eq!(student_path(dir, "小明", "jsonl"), dir.join("小明.jsonl"));
+        assert_eq!(student_path(dir, "a.b", "jsonl"), dir.join("a%2Eb.jsonl"));
+        assert_eq!(student_path(dir, "../a", "json"), dir.join("%2E%2E%2Fa.json"));
+        // 转义后不会与其他学生名重复
+        assert_ne!(student_path(dir, "a.b", "jsonl"), student_path(dir, "a_b", "jsonl"));
+        assert_ne!(student_path(dir, "a.b", "jsonl"), student_path(dir, "a%2Eb", "jsonl"));
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("1 2"), "1 2");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!(split_csv_line("1,\"a,\"\"b\"\"\",,2"), vec!["1", "a,\"b\"", "", "2"]);
+    }
+
+    #[test]
+    fn test_records_from_csv() {
+        let records = vec![record(9, Op::Add, 7, false, 3000), record(13, Op::Minus, 5, true, 1200)];
+        let parsed = records_from_csv(&records_to_csv(&records)).unwrap();
+        assert_eq!(parsed.iter().map(|r| (r.fact, r.correct, r.elapsed_ms)).collect::<Vec<_>>(),
+                   records.iter().map(|r| (r.fact, r.correct, r.elapsed_ms)).collect::<Vec<_>>());
+
+        // 团队提供的结果文件，列的顺序不限，elapsed_ms 可省略
+        let parsed = records_from_csv("correct,left,right,op\n1,8,6,+\n0,12,4,-\n").unwrap();
+        assert_eq!(parsed[0].fact, Some(Fact { left: 8, op: Op::Add, right: 6 }));
+        assert!(parsed[0].correct && !parsed[1].correct);
+        assert_eq!(parsed[1].elapsed_ms, 0);
+
+        assert!(records_from_csv("left,right\n1,2\n").is_err());
+        assert!(records_from_csv("left,op,right,correct\n1,?,2,true\n").is_err());
+    }
+}
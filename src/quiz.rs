@@ -0,0 +1,178 @@
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+use crate::QuizOpts;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::problem::{bond_texts, grid_text, grid_texts, written_text, Problem, Segment};
+use crate::progress::{now, Record, Store};
+use crate::utils::new_rng;
+
+// 最慢的几道题
+const SLOWEST_COUNT: usize = 3;
+
+// 一道题的作答记录
+#[derive(Debug)]
+pub struct Attempt {
+    pub problem: Problem,
+    pub given: Vec<String>,
+    pub correct: bool,
+    pub elapsed: Duration,
+}
+
+impl QuizOpts {
+    pub fn run_quiz(&self) -> Result<(), Error> {
+        self.generator.print_adaptive_weights();
+        let mut rng = new_rng(self.generator.sheet().seed);
+        let problems = self.generator.gen_section(None, &mut rng)?.problems;
+        // 终端中无法显示钟面
+        if problems.iter().any(|p| p.has_clock()) {
+            Err("error.quiz_clock")?;
+        }
+        let stdin = io::stdin();
+        let attempts = ask_all(&problems, &mut stdin.lock(), &mut io::stdout())?;
+        print_summary(&attempts, problems.len(), &mut io::stdout())?;
+
+        if let Some(student) = &self.student {
+            let timestamp = now();
+            let records: Vec<Record> = attempts.iter().map(|a| Record::from_attempt(a, timestamp)).collect();
+            Store::new(&self.store.data_dir).append(student, &records)?;
+            println!("{}", tf("quiz.saved", &[("count", &records.len()), ("student", student)]));
+        }
+        Ok(())
+    }
+}
+
+// 逐题提问，输入 q 或者输入结束时提前退出
+pub fn ask_all<R: BufRead, W: Write>(problems: &[Problem], input: &mut R, output: &mut W) -> io::Result<Vec<Attempt>> {
+    let mut attempts = vec![];
+    for (i, problem) in problems.iter().enumerate() {
+        write!(output, "({}/{}) {} ", i + 1, problems.len(), prompt_text(problem))?;
+        output.flush()?;
+
+        let start = Instant::now();
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+            writeln!(output)?;
+            break;
+        }
+        let elapsed = start.elapsed();
+
+        let given = split_answers(&line);
+        let correct = problem.is_correct(&given);
+        let seconds = format!("{:.1}", elapsed.as_secs_f64());
+        if correct {
+            writeln!(output, "{}", tf("quiz.correct", &[("seconds", &seconds)]))?;
+        } else {
+            writeln!(output, "{}", tf("quiz.wrong", &[("answer", &problem.answers.join(" ")), ("seconds", &seconds)]))?;
+        }
+        attempts.push(Attempt { problem: problem.clone(), given, correct, elapsed });
+    }
+    Ok(attempts)
+}
+
+pub fn print_summary<W: Write>(attempts: &[Attempt], total: usize, output: &mut W) -> io::Result<()> {
+    let correct = attempts.iter().filter(|a| a.correct).count();
+    let accuracy = if attempts.is_empty() { 0.0 } else { correct as f64 * 100.0 / attempts.len() as f64 };
+    let total_time: Duration = attempts.iter().map(|a| a.elapsed).sum();
+    writeln!(output, "{}", tf("quiz.summary", &[("answered", &attempts.len()), ("total", &total), ("correct", &correct),
+                                                ("percent", &format!("{:.1}", accuracy)), ("time", &format_duration(total_time))]))?;
+
+    let mut slowest: Vec<&Attempt> = attempts.iter().collect();
+    slowest.sort_by_key(|a| std::cmp::Reverse(a.elapsed));
+    if !slowest.is_empty() {
+        writeln!(output, "{}", t("quiz.slowest"))?;
+        for a in slowest.iter().take(SLOWEST_COUNT) {
+            writeln!(output, "  {}  {:.1}s", a.problem.solved_text().trim(), a.elapsed.as_secs_f64())?;
+        }
+    }
+
+    let mistakes: Vec<&Attempt> = attempts.iter().filter(|a| !a.correct).collect();
+    if !mistakes.is_empty() {
+        writeln!(output, "{}", t("quiz.mistakes"))?;
+        for a in mistakes {
+            writeln!(output, "  {}", tf("quiz.given", &[("problem", &a.problem.solved_text().trim()), ("given", &a.given.join(" "))]))?;
+        }
+    }
+    Ok(())
+}
+
+// 终端中显示的题目，空白处显示为 ?
+pub fn prompt_text(problem: &Problem) -> String {
+    let text: String = problem.segments.iter().map(|segment| match segment {
+        Segment::Text(s) | Segment::Fraction(s) => s.clone(),
+        Segment::Blank(_) => "?".to_string(),
+        Segment::Clock { .. } => String::new(),
+        Segment::Bond { whole, parts } => {
+            let [whole, l, r] = bond_texts(whole, parts, || "?".to_string());
+            format!("{} = {} + {}", whole, l, r)
+        }
+        Segment::Grid { rows, columns, cells } => format!("\n{}\n", grid_text(&grid_texts(rows, columns, cells, || "?".to_string()))),
+        Segment::Written { op, left, right } => written_text(*op, *left, *right, "?"),
+    }).collect();
+    // 方格前后的换行保留，方格从新的一行开始
+    text.trim_matches(' ').to_string()
+}
+
+// 多个答案可以用空格或逗号分隔
+pub fn split_answers(line: &str) -> Vec<String> {
+    line.split(|c: char| c.is_whitespace() || c == ',' || c == '，')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs_f64();
+    if secs < 60.0 {
+        format!("{:.1}s", secs)
+    } else {
+        format!("{}m {:.1}s", (secs / 60.0) as u64, secs % 60.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use crate::problem::{Problem, Segment};
+    use crate::quiz::{ask_all, print_summary, prompt_text, split_answers};
+
+    #[test]
+    fn test_prompt_text() {
+        let p = Problem::expr(" 3 + 4 =".to_string(), "7".to_string());
+        assert_eq!(prompt_text(&p), "3 + 4 = ?");
+        let p = Problem::joined(vec![Segment::Text("1".to_string()), Segment::Blank(1), Segment::Blank(1), Segment::Text("4".to_string())],
+                                vec!["2".to_string(), "3".to_string()]);
+        assert_eq!(prompt_text(&p), "1 ? ? 4");
+    }
+
+    #[test]
+    fn test_split_answers() {
+        assert_eq!(split_answers(" 12 \n"), vec!["12"]);
+        assert_eq!(split_answers("2, 3，4"), vec!["2", "3", "4"]);
+        assert!(split_answers("\n").is_empty());
+    }
+
+    #[test]
+    fn test_ask_all() {
+        let problems = vec![
+            Problem::expr("3 + 4 =".to_string(), "7".to_string()),
+            Problem::expr("9 - 4 =".to_string(), "5".to_string()),
+            Problem::expr("2 + 2 =".to_string(), "4".to_string()),
+        ];
+        let mut output = vec![];
+        let attempts = ask_all(&problems, &mut Cursor::new("7\n6\nq\n"), &mut output).unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts[0].correct);
+        assert!(!attempts[1].correct);
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("(1/3) 3 + 4 = ? 答对了！"));
+        assert!(output.contains("答错了，答案是 5"));
+
+        let mut summary = vec![];
+        print_summary(&attempts, problems.len(), &mut summary).unwrap();
+        let summary = String::from_utf8(summary).unwrap();
+        assert!(summary.starts_with("作答 2/3，答对 1 (50.0%)"));
+        assert!(summary.contains("9 - 4 = 5  (你的答案 6)"));
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use crate::{RoundPlace, RoundingOpts, RoundingRule};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 题目中的数的上限，取整后的两个数相加也不会超出 i64
+pub const MAX_NUMBER: i64 = 1_000_000_000_000_000_000;
+
+impl RoundPlace {
+    pub fn multiple(&self) -> i64 {
+        match self {
+            RoundPlace::Ten => 10,
+            RoundPlace::Hundred => 100,
+            RoundPlace::Thousand => 1000,
+            RoundPlace::TenThousand => 10000,
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            RoundPlace::Ten => t("rounding.ten"),
+            RoundPlace::Hundred => t("rounding.hundred"),
+            RoundPlace::Thousand => t("rounding.thousand"),
+            RoundPlace::TenThousand => t("rounding.ten_thousand"),
+        }
+    }
+}
+
+impl RoundingRule {
+    // 取最接近的 multiple 的倍数，恰好在中间时按规则取舍
+    pub fn round(&self, number: i64, multiple: i64) -> i64 {
+        let times = number.div_euclid(multiple);
+        let r = number.rem_euclid(multiple);
+        let up = match r.cmp(&(multiple - r)) {
+            Ordering::Less => false,
+            Ordering::Greater => true,
+            Ordering::Equal => match self {
+                RoundingRule::HalfUp => number >= 0,
+                RoundingRule::HalfEven => times % 2 != 0,
+            },
+        };
+        if up { (times + 1) * multiple } else { times * multiple }
+    }
+}
+
+impl RoundingOpts {
+    pub fn gen_rounding_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/rounding.docx", Source::new("rounding", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        (0..self.count).map(|_| {
+            let c = &self.category;
+            if c.starts_with('r') {
+                self.gen_rounding(rng)
+            } else if c.starts_with('+') {
+                self.gen_estimation(Op::Add, rng)
+            } else if c.starts_with('_') {
+                self.gen_estimation(Op::Minus, rng)
+            } else if c.starts_with('e') {
+                self.gen_estimation(*[Op::Add, Op::Minus].choose(rng).unwrap(), rng)
+            } else {
+                match rng.random_range(0..3) {
+                    0 => self.gen_rounding(rng),
+                    1 => self.gen_estimation(Op::Add, rng),
+                    _ => self.gen_estimation(Op::Minus, rng),
+                }
+            }
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        let c = &self.category;
+        if c.starts_with('r') {
+            t("heading.rounding")
+        } else if c.starts_with(['e', '+', '_']) {
+            t("heading.estimation")
+        } else {
+            t("heading.rounding_estimation")
+        }
+    }
+
+    // 随机选取精确到的数位，只选不超过范围最大值的数位，都超过时取最小的
+    fn random_place(&self, rng: &mut impl Rng) -> RoundPlace {
+        let max = self.number_max_inclusive;
+        let candidates: Vec<RoundPlace> = self.places.iter().copied().filter(|p| p.multiple() <= max).collect();
+        match candidates.choose(rng) {
+            Some(place) => *place,
+            None => *self.places.iter().min_by_key(|p| p.multiple()).unwrap(),
+        }
+    }
+
+    // 范围内不小于 multiple 的数，范围允许时不取 multiple 的倍数，避免近似数就是它本身
+    fn random_number(&self, multiple: i64, rng: &mut impl Rng) -> i64 {
+        let min = self.number_min_inclusive.max(multiple).min(self.number_max_inclusive);
+        let max = self.number_max_inclusive;
+        loop {
+            let number = rng.random_range(min..=max);
+            if min == max || number % multiple != 0 {
+                return number;
+            }
+        }
+    }
+
+    // 347 精确到十位约是 __
+    fn gen_rounding(&self, rng: &mut impl Rng) -> Problem {
+        let place = self.random_place(rng);
+        let number = self.random_number(place.multiple(), rng);
+        let answer = self.rule.round(number, place.multiple()).to_string();
+        let question = tf("rounding.round", &[("number", &number), ("place", &place.text())]);
+        Problem { segments: vec![Segment::Text(question + " "), Segment::Blank(answer.len() as u16)], answers: vec![answer], fact: None }
+    }
+
+    // 精确到百位估算：398 + 215 ≈ __ + __ = __，减法时被减数较大
+    fn gen_estimation(&self, op: Op, rng: &mut impl Rng) -> Problem {
+        let place = self.random_place(rng);
+        let multiple = place.multiple();
+        let mut l = self.random_number(multiple, rng);
+        let mut r = self.random_number(multiple, rng);
+        if op == Op::Minus && l < r {
+            (l, r) = (r, l);
+        }
+        let (rl, rr) = (self.rule.round(l, multiple), self.rule.round(r, multiple));
+        let estimate = if op == Op::Minus { rl - rr } else { rl + rr };
+        let question = tf("rounding.estimate", &[("place", &place.text())]);
+        let answers = [rl, rr, estimate].map(|n| n.to_string());
+        let blank = |n: &String| Segment::Blank(n.len() as u16);
+        Problem {
+            segments: vec![
+                Segment::Text(format!("{}{} {} {} ≈ ", question, l, op.symbol(), r)),
+                blank(&answers[0]),
+                Segment::Text(format!(" {} ", op.symbol())),
+                blank(&answers[1]),
+                Segment::Text(" = ".to_string()),
+                blank(&answers[2]),
+            ],
+            answers: answers.to_vec(),
+            fact: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use crate::{Cli, RoundPlace, RoundingOpts, RoundingRule};
+    use crate::utils::new_rng;
+
+    #[test]
+    fn test_round() {
+        let up = RoundingRule::HalfUp;
+        let even = RoundingRule::HalfEven;
+        assert_eq!(up.round(347, 10), 350);
+        assert_eq!(up.round(344, 10), 340);
+        assert_eq!(up.round(15, 10), 20);
+        assert_eq!(up.round(17, 5), 15);
+        assert_eq!(up.round(350, 100), 400);
+        assert_eq!(up.round(-15, 10), -20);
+        assert_eq!(up.round(-14, 10), -10);
+        assert_eq!(even.round(25, 10), 20);
+        assert_eq!(even.round(35, 10), 40);
+        assert_eq!(even.round(250, 100), 200);
+        assert_eq!(even.round(251, 100), 300);
+        assert_eq!(even.round(-25, 10), -20);
+        assert_eq!(even.round(-35, 10), -40);
+    }
+
+    #[test]
+    fn test_gen_rounding() {
+        let opts = RoundingOpts {
+            count: 40,
+            category: "r".to_string(),
+            places: vec![RoundPlace::Ten, RoundPlace::Hundred],
+            rule: RoundingRule::HalfEven,
+            number_min_inclusive: 10,
+            number_max_inclusive: 999,
+            ..Default::default()
+        };
+        for problem in opts.gen_problems(&mut new_rng(Some(11))) {
+            // 347 精确到十位约是 350
+            let text = problem.solved_text();
+            let number: i64 = text.split_whitespace().next().unwrap().parse().unwrap();
+            let multiple = if text.contains("十位") { 10 } else { 100 };
+            let answer: i64 = problem.answers[0].parse().unwrap();
+            assert!(number % multiple != 0 && (10..=999).contains(&number), "{}", text);
+            assert_eq!(answer % multiple, 0);
+            assert!((answer - number).abs() * 2 <= multiple, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_gen_estimation() {
+        let opts = RoundingOpts {
+            count: 40,
+            category: "e".to_string(),
+            places: vec![RoundPlace::Ten, RoundPlace::Hundred],
+            number_min_inclusive: 10,
+            number_max_inclusive: 999,
+            ..Default::default()
+        };
+        for problem in opts.gen_problems(&mut new_rng(Some(12))) {
+            // 精确到百位估算：398 + 215 ≈ 400 + 200 = 600
+            let text = problem.solved_text();
+            let (_, expr) = text.split_once('：').unwrap();
+            let tokens: Vec<&str> = expr.split_whitespace().collect();
+            let [l, r, rl, rr, estimate] = [0, 2, 4, 6, 8].map(|i| tokens[i].parse::<i64>().unwrap());
+            let multiple = if text.contains("十位") { 10 } else { 100 };
+            assert_eq!((opts.rule.round(l, multiple), opts.rule.round(r, multiple)), (rl, rr), "{}", text);
+            let expected = if tokens[1] == "+" { rl + rr } else { rl - rr };
+            assert_eq!(estimate, expected, "{}", text);
+            assert!(expected >= 0);
+        }
+
+        // 取整后相加可能溢出的数在解析参数时拒绝
+        assert!(Cli::try_parse_from(["gen_arithmatic", "rounding", "-r", "9223372036854775807", "-t", "ten-thousand"]).is_err());
+        let opts = RoundingOpts { places: vec![RoundPlace::TenThousand], number_max_inclusive: crate::rounding::MAX_NUMBER, ..opts };
+        assert_eq!(opts.gen_problems(&mut new_rng(Some(13))).len(), 40);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use clap::{Arg, Command, CommandFactory, Parser};
+use crate::i18n::{lang, localize_command, t, tf};
+use crate::{LayoutOpts, SectionCli, ServeOpts, SheetOpts};
+use crate::err::Error;
+use crate::layout::{answer_key_path, write_docx, Version};
+use crate::manifest::to_html;
+use crate::utils::escape_html;
+
+// 页面中不提供的参数：下载时所有版本总是写入同一个文件
+const HIDDEN_ARGS: [&str; 3] = ["help", "single_file", "combined_key"];
+
+// 读取请求的超时时间，避免不发送请求的连接一直占用线程
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 处理请求的线程数，同时到达的更多连接排队等待
+const WORKERS: usize = 4;
+
+// 下载请求的序号，每个请求写入各自的临时目录，同时下载时互不删除对方的文件
+static DOWNLOAD_ID: AtomicUsize = AtomicUsize::new(0);
+
+// 一个 HTTP 响应
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    // 下载时的文件名
+    filename: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn html(status: &'static str, body: String) -> Response {
+        Response { status, content_type: "text/html; charset=utf-8", filename: None, body: body.into_bytes() }
+    }
+}
+
+impl ServeOpts {
+    pub fn serve(&self) -> Result<(), Error> {
+        let listener = TcpListener::bind((self.host.as_str(), self.port))?;
+        println!("{}", tf("serve.listening", &[("host", &self.host), ("port", &self.port)]));
+        // 固定数量的线程处理请求，某个请求出错或卡住时不影响其他请求
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(WORKERS);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..WORKERS {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let stream = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return,
+                };
+                let Ok(stream) = stream else { return };
+                if let Err(e) = handle(stream) {
+                    eprintln!("{}", tf("error.prefix", &[("error", &e)]));
+                }
+            });
+        }
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => eprintln!("{}", tf("error.prefix", &[("error", &e)])),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // 忽略请求头
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => route(target),
+        _ => Response::html("405 Method Not Allowed", page("405", &format!("<p>{}</p>", t("serve.get_only")))),
+    };
+
+    let mut head = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+                           response.status, response.content_type, response.body.len());
+    if let Some(filename) = &response.filename {
+        head += &format!("Content-Disposition: attachment; filename=\"{}\"\r\n", filename);
+    }
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(b"\r\n")?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn route(target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+    match path {
+        "/" => Response::html("200 OK", index(&params, None)),
+        "/preview" => preview(&params),
+        "/download" => download(&params),
+        _ => Response::html("404 Not Found", page("404", &format!("<p>{}</p>", t("serve.not_found")))),
+    }
+}
+
+// 不属于生成器的参数
+const PAGE_PARAMS: [&str; 3] = ["generator", "title", "file"];
+
+// 表单参数：generator 为生成器子命令，title 为试卷标题，file 为下载的文件，其余与命令行的长参数名相同
+struct Request {
+    section: SectionCli,
+    title: Option<String>,
+    seed: u64,
+    // 与命令行相同，第i个版本的种子为 seed + i
+    versions: Vec<Version>,
+}
+
+fn parse_request(params: &[(String, String)]) -> Result<Request, String> {
+    let generator = param(params, "generator").ok_or_else(|| t("error.missing_generator"))?;
+    let mut args = vec![generator.to_string()];
+    for (name, value) in params {
+        if PAGE_PARAMS.contains(&name.as_str()) || value.is_empty() {
+            continue;
+        }
+        let long = format!("--{}", name.replace('_', "-"));
+        // 复选框
+        if value == "on" {
+            args.push(long);
+        } else {
+            args.push(format!("{}={}", long, value));
+        }
+    }
+    args.push("--single-file".to_string());
+    let section = SectionCli::try_parse_from(args).map_err(|e| e.render().to_string())?;
+    // 随机选取的种子显示在预览页面上，不打印到服务端
+    let seed = section.generator.sheet().seed.unwrap_or_else(rand::random);
+    let title = param(params, "title").filter(|t| !t.is_empty()).map(|t| t.to_string());
+    let mut request = Request { section, title, seed, versions: vec![] };
+    // 参数之间互相矛盾（比如最小值大于最大值）时生成不出题目，与参数格式错误一样提示
+    let generator = &request.section.generator;
+    let heading = &request.section.heading;
+    request.versions = request.sheet().gen_versions(|rng| Ok(vec![generator.gen_section(heading.clone(), rng)?])).map_err(|e| e.to_string())?;
+    Ok(request)
+}
+
+impl Request {
+
+    fn sheet(&self) -> SheetOpts {
+        SheetOpts { seed: Some(self.seed), single_file: true, ..self.section.generator.sheet().clone() }
+    }
+
+    fn layout(&self) -> &LayoutOpts {
+        self.section.generator.layout()
+    }
+}
+
+fn preview(params: &[(String, String)]) -> Response {
+    let request = match parse_request(params) {
+        Ok(request) => request,
+        Err(e) => return Response::html("400 Bad Request", index(params, Some(&e))),
+    };
+    let versions = &request.versions;
+    let title = request.title.as_deref();
+    let mut html = to_html(versions, request.layout(), title, false);
+    if request.layout().answer_key {
+        let key = to_html(versions, request.layout(), title, true);
+        let body = &key[key.find("<body>\n").map(|i| i + 7).unwrap_or(0)..key.rfind("</body>").unwrap_or(key.len())];
+        html = html.replace("</body>", &format!("<hr>\n{}</body>", body));
+    }
+
+    // 下载链接带上预览时使用的种子，保证下载的与预览的相同
+    let mut link_params: Vec<(String, String)> = params.iter().filter(|(k, _)| k != "seed").cloned().collect();
+    link_params.push(("seed".to_string(), request.seed.to_string()));
+    let query = encode_query(&link_params);
+    let mut nav = format!("<nav><a href=\"/?{q}\">{back}</a> | <a href=\"/download?{q}&amp;file=docx\">{docx}</a>", q = escape_html(&query),
+                          back = t("serve.back"), docx = t("serve.download_docx"));
+    if request.layout().answer_key {
+        nav += &format!(" | <a href=\"/download?{}&amp;file=answers\">{}</a>", escape_html(&query), t("serve.download_answers"));
+    }
+    nav += &format!(" | seed {}</nav>\n", request.seed);
+    Response::html("200 OK", html.replacen("<body>\n", &format!("<body>\n{}", nav), 1))
+}
+
+fn download(params: &[(String, String)]) -> Response {
+    let request = match parse_request(params) {
+        Ok(request) => request,
+        Err(e) => return Response::html("400 Bad Request", index(params, Some(&e))),
+    };
+    let generator = param(params, "generator").unwrap_or("worksheet");
+    let answers = param(params, "file") == Some("answers");
+
+    let id = DOWNLOAD_ID.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("gen_arithmatic-serve-{}-{}", std::process::id(), id));
+    let filepath = dir.join(format!("{}.docx", generator)).to_string_lossy().to_string();
+    let mut layout = request.layout().clone();
+    layout.answer_key = answers;
+    let result = fs::create_dir_all(&dir).and_then(|_| {
+        write_docx(&request.versions, &request.sheet(), &layout, request.title.as_deref(), &filepath);
+        fs::read(if answers { answer_key_path(&filepath) } else { filepath.clone() })
+    });
+    let _ = fs::remove_dir_all(&dir);
+
+    match result {
+        Ok(body) => Response {
+            status: "200 OK",
+            content_type: "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            filename: Some(if answers { answer_key_path(&format!("{}.docx", generator)) } else { format!("{}.docx", generator) }),
+            body,
+        },
+        Err(e) => Response::html("500 Internal Server Error", page("500", &format!("<p>{}</p>", escape_html(&e.to_string())))),
+    }
+}
+
+// 每个生成器一个表单，字段与命令行参数一一对应
+fn index(params: &[(String, String)], error: Option<&str>) -> String {
+    let mut body = String::from("<h1>gen_arithmatic</h1>\n");
+    let selected = param(params, "generator");
+    let command = localize_command(SectionCli::command());
+    for generator in command.get_subcommands() {
+        let name = generator.get_name();
+        body += &format!("<form action=\"/preview\" method=\"get\">\n<fieldset>\n<legend>{} {}</legend>\n",
+                         name, escape_html(&generator.get_about().map(|a| a.to_string()).unwrap_or_default()));
+        // 提交了这个生成器的表单时，保留填写的内容并显示错误
+        let values = if selected == Some(name) { params } else { &[] };
+        if let (Some(error), true) = (error, selected == Some(name)) {
+            body += &format!("<pre class=\"error\">{}</pre>\n", escape_html(error));
+        }
+        body += &format!("<input type=\"hidden\" name=\"generator\" value=\"{}\">\n", name);
+        body += &format!("<label>{} <input name=\"title\" value=\"{}\"></label><br>\n", t("serve.title"), escape_html(param(values, "title").unwrap_or("")));
+        for arg in visible_args(generator) {
+            body += &field(arg, values);
+        }
+        body += &format!("<button type=\"submit\">{}</button>\n</fieldset>\n</form>\n", t("serve.preview"));
+    }
+    page("gen_arithmatic", &body)
+}
+
+fn visible_args(command: &Command) -> impl Iterator<Item = &Arg> {
+    command.get_arguments().filter(|a| !HIDDEN_ARGS.contains(&a.get_id().as_str()) && a.get_long().is_some())
+}
+
+fn field(arg: &Arg, values: &[(String, String)]) -> String {
+    let name = arg.get_id().as_str();
+    let long = arg.get_long().unwrap_or(name);
+    let default = arg.get_default_values().first().map(|v| v.to_string_lossy().to_string()).unwrap_or_default();
+    let value = param(values, name).map(|v| v.to_string());
+    let required = if arg.is_required_set() { " required" } else { "" };
+
+    let input = if !arg.get_action().takes_values() {
+        let checked = if value.as_deref() == Some("on") { " checked" } else { "" };
+        format!("<input type=\"checkbox\" name=\"{}\"{}>", name, checked)
+    } else if !arg.get_possible_values().is_empty() {
+        let current = value.unwrap_or(default);
+        let options: String = arg.get_possible_values().iter().map(|v| {
+            let selected = if v.get_name() == current { " selected" } else { "" };
+            format!("<option{}>{}</option>", selected, v.get_name())
+        }).collect();
+        format!("<select name=\"{}\">{}</select>", name, options)
+    } else {
+        format!("<input name=\"{}\" value=\"{}\" placeholder=\"{}\"{}>", name,
+                escape_html(&value.unwrap_or_default()), escape_html(&default), required)
+    };
+    let help = arg.get_help().map(|h| h.to_string()).unwrap_or_default();
+    format!("<label title=\"{}\">{} {}</label><br>\n", escape_html(&help), long, input)
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+             fieldset {{ margin-bottom: 1em; }}\nlabel {{ display: inline-block; margin: 2px 0; }}\n.error {{ color: #FF0000; }}\n\
+             </style>\n</head>\n<body>\n{}</body>\n</html>\n", lang(), escape_html(title), body)
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 2;
+            }
+            (b, _) => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn encode_query(params: &[(String, String)]) -> String {
+    params.iter().map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v))).collect::<Vec<_>>().join("&")
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b' ' => "+".to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::serve::{encode_query, parse_query, parse_request, percent_decode, route};
+
+    fn params(query: &str) -> Vec<(String, String)> {
+        parse_query(query)
+    }
+
+    #[test]
+    fn test_query() {
+        assert_eq!(percent_decode("10*%2C5*+x%E5%8A%A0%"), "10*,5* x加%");
+        let params = vec![("operand_pattern".to_string(), "10*,5*".to_string()), ("title".to_string(), "每日 练习".to_string())];
+        assert_eq!(parse_query(&encode_query(&params)), params);
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request(&params("generator=add-minus&count=4&category=%2B&operand_pattern=&numbering=arabic&answer_key=on&seed=7&title=T")).unwrap();
+        assert_eq!((request.seed, request.title.as_deref()), (7, Some("T")));
+        assert!(request.layout().answer_key);
+        assert_eq!(request.versions[0].sections[0].problems.len(), 4);
+
+        let error = parse_request(&params("generator=add-minus&category=%2B&operand_pattern=7~3")).err().unwrap();
+        assert!(error.contains("范围的起点大于终点"));
+        assert!(parse_request(&params("generator=add-minus")).is_err());
+        assert_eq!(parse_request(&params("count=3")).err().as_deref(), Some("缺少生成器"));
+        assert!(parse_request(&params("generator=add-minus&category=%2B&file=answers")).is_ok());
+    }
+
+    #[test]
+    fn test_route() {
+        let index = String::from_utf8(route("/").body).unwrap();
+        assert!(index.contains("<legend>add-minus"));
+        assert!(index.contains("<legend>missing-number"));
+        assert!(index.contains("<select name=\"numbering\">"));
+        assert!(!index.contains("name=\"single_file\""));
+
+        let response = route("/preview?generator=missing-number&count=2&seed=3&answer_key=on");
+        assert_eq!(response.status, "200 OK");
+        let html = String::from_utf8(response.body).unwrap();
+        assert!(html.contains("file=answers"));
+        assert!(html.contains("seed 3"));
+
+        let response = route("/preview?generator=add-minus&category=%2B&operand_pattern=0*");
+        assert_eq!(response.status, "400 Bad Request");
+        assert!(String::from_utf8(response.body).unwrap().contains("倍数必须大于 0"));
+        let response = route("/preview?generator=add-minus&category=%2B&number_min_inclusive=10&number_max_inclusive=5");
+        assert_eq!(response.status, "400 Bad Request");
+        assert!(String::from_utf8(response.body).unwrap().contains("class=\"error\""));
+        assert_eq!(route("/nothing").status, "404 Not Found");
+    }
+
+    #[test]
+    fn test_concurrent_download() {
+        let downloads: Vec<_> = (0..4).map(|_| std::thread::spawn(|| route("/download?generator=add-minus&category=%2B&count=40&seed=1"))).collect();
+        for download in downloads {
+            let response = download.join().unwrap();
+            assert_eq!(response.status, "200 OK");
+            assert!(response.body.starts_with(b"PK"));
+        }
+    }
+}
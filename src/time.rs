@@ -0,0 +1,229 @@
+use rand::Rng;
+use crate::{ClockMode, HourFormat, TimeOpts};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 一天的分钟数，时刻都在同一天之内，不跨过午夜
+const DAY_MINUTES: u16 = 24 * 60;
+
+// 一天中的第 minutes 分钟，比如 580 -> 9:40，12 小时制时 21:05 -> 下午9:05
+pub fn time_text(minutes: u16, format: HourFormat) -> String {
+    let (hour, minute) = (minutes / 60, minutes % 60);
+    match format {
+        HourFormat::H24 => format!("{}:{:02}", hour, minute),
+        HourFormat::H12 => {
+            let key = if hour < 12 { "time.am" } else { "time.pm" };
+            tf(key, &[("time", &clock_text(minutes))])
+        }
+    }
+}
+
+// 钟面上读出的时间，不分上午下午，比如 0:05 -> 12:05
+pub fn clock_text(minutes: u16) -> String {
+    let hour = match minutes / 60 % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    format!("{}:{:02}", hour, minutes % 60)
+}
+
+// 一段时间，比如 95 -> 1小时35分钟
+pub fn duration_text(minutes: u16) -> String {
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    match (hours, minutes) {
+        (0, _) => tf("time.minutes", &[("m", &minutes)]),
+        (_, 0) => tf("time.hours", &[("h", &hours)]),
+        _ => tf("time.hours_minutes", &[("h", &hours), ("m", &minutes)]),
+    }
+}
+
+impl TimeOpts {
+    pub fn gen_time_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/time.docx", Source::new("time", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        (0..self.count).map(|_| match self.clock {
+            ClockMode::Read => self.gen_read(rng),
+            ClockMode::Draw => self.gen_draw(rng),
+            ClockMode::None if self.category.starts_with('e') => self.gen_between(rng),
+            ClockMode::None if self.category.starts_with('+') => self.gen_elapsed(Op::Add, rng),
+            ClockMode::None if self.category.starts_with('_') => self.gen_elapsed(Op::Minus, rng),
+            ClockMode::None if rng.random() => self.gen_elapsed(Op::Add, rng),
+            ClockMode::None => self.gen_elapsed(Op::Minus, rng),
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        match self.clock {
+            ClockMode::Read => t("heading.clock_read"),
+            ClockMode::Draw => t("heading.clock_draw"),
+            ClockMode::None => t("heading.time"),
+        }
+    }
+
+    // 一天中 minute_step 的倍数的时刻
+    fn random_time(&self, rng: &mut impl Rng) -> u16 {
+        rng.random_range(0..DAY_MINUTES.div_ceil(self.minute_step)) * self.minute_step
+    }
+
+    // 不超过 max_elapsed 的一段时间，至少 minute_step 分钟
+    fn random_duration(&self, rng: &mut impl Rng) -> u16 {
+        rng.random_range(1..=(self.max_elapsed / self.minute_step).max(1)) * self.minute_step
+    }
+
+    // --carry 时分钟必须进位或退位，minute_step 为 60 时不可能，忽略
+    fn carry_ok(&self, carried: bool) -> bool {
+        carried || !self.carry || self.minute_step == 60
+    }
+
+    // 9:40 经过 35分钟 是 10:15，或者 10:15 的 35分钟前是 9:40
+    fn gen_elapsed(&self, op: Op, rng: &mut impl Rng) -> Problem {
+        loop {
+            let (start, duration) = (self.random_time(rng), self.random_duration(rng));
+            let (end, carried) = match op {
+                Op::Minus => (start.checked_sub(duration), start % 60 < duration % 60),
+                _ => (Some(start + duration).filter(|end| *end < DAY_MINUTES), start % 60 + duration % 60 >= 60),
+            };
+            let Some(end) = end.filter(|_| self.carry_ok(carried)) else { continue };
+            let key = if op == Op::Minus { "time.before" } else { "time.after" };
+            let question = tf(key, &[("time", &time_text(start, self.hour_format)), ("duration", &duration_text(duration))]);
+            return Problem::expr(question, time_text(end, self.hour_format));
+        }
+    }
+
+    // 从 9:40 到 11:15 经过了 1小时35分钟
+    fn gen_between(&self, rng: &mut impl Rng) -> Problem {
+        loop {
+            let (start, duration) = (self.random_time(rng), self.random_duration(rng));
+            let end = start + duration;
+            if end >= DAY_MINUTES || !self.carry_ok(start % 60 > end % 60) {
+                continue;
+            }
+            let question = tf("time.between", &[("start", &time_text(start, self.hour_format)), ("end", &time_text(end, self.hour_format))]);
+            return Problem::expr(question, duration_text(duration));
+        }
+    }
+
+    // 读出钟面上的时间
+    fn gen_read(&self, rng: &mut impl Rng) -> Problem {
+        let minutes = self.random_time(rng) % (12 * 60);
+        Problem {
+            segments: vec![Segment::Clock { minutes, hands: true }, Segment::Text(" ".to_string()), Segment::Blank(5)],
+            answers: vec![clock_text(minutes)],
+            fact: None,
+        }
+    }
+
+    // 按给出的时间在空白钟面上画出指针，答案页中画好指针
+    fn gen_draw(&self, rng: &mut impl Rng) -> Problem {
+        let minutes = self.random_time(rng) % (12 * 60);
+        Problem {
+            segments: vec![Segment::Text(format!("{} ", clock_text(minutes))), Segment::Clock { minutes, hands: false }],
+            answers: vec![],
+            fact: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ClockMode, HourFormat, TimeOpts};
+    use crate::problem::Segment;
+    use crate::time::{clock_text, duration_text, time_text};
+    use crate::utils::new_rng;
+
+    // "9:40" 或 "下午9:40" -> 一天中的分钟数
+    fn minutes(text: &str) -> u16 {
+        let (h, m) = text.trim_start_matches("上午").trim_start_matches("下午").split_once(':').unwrap();
+        let h: u16 = h.parse().unwrap();
+        let h = match (text.starts_with("上午") || text.starts_with("下午"), h) {
+            (true, 12) => 0,
+            _ => h,
+        };
+        let h = if text.starts_with("下午") { h + 12 } else { h };
+        h * 60 + m.parse::<u16>().unwrap()
+    }
+
+    // "1小时35分钟" -> 95
+    fn duration(text: &str) -> u16 {
+        let text = text.trim_end_matches("前是").trim_end_matches("分钟");
+        match text.split_once("小时") {
+            Some((h, "")) => h.parse::<u16>().unwrap() * 60,
+            Some((h, m)) => h.parse::<u16>().unwrap() * 60 + m.parse::<u16>().unwrap(),
+            None => text.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_time_text() {
+        assert_eq!(time_text(580, HourFormat::H24), "9:40");
+        assert_eq!(time_text(21 * 60 + 5, HourFormat::H12), "下午9:05");
+        assert_eq!(time_text(5, HourFormat::H12), "上午12:05");
+        assert_eq!(clock_text(13 * 60), "1:00");
+        assert_eq!(duration_text(95), "1小时35分钟");
+        assert_eq!(duration_text(120), "2小时");
+        assert_eq!(duration_text(35), "35分钟");
+    }
+
+    #[test]
+    fn test_gen_elapsed() {
+        let opts = TimeOpts { count: 40, category: "x".to_string(), minute_step: 5, max_elapsed: 180, carry: true, ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(3))) {
+            let text = problem.solved_text();
+            // 9:40 经过 35分钟 是 10:15 / 10:15 的 35分钟前是 9:40
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            let (start, duration, end) = (minutes(tokens[0]), duration(tokens[2]), minutes(tokens.last().unwrap()));
+            if text.contains("经过") {
+                assert_eq!(start + duration, end);
+                assert!(start % 60 + duration % 60 >= 60);
+            } else {
+                assert_eq!(start - duration, end);
+                assert!(start % 60 < duration % 60);
+            }
+            assert!(duration <= 180 && duration % 5 == 0);
+        }
+    }
+
+    #[test]
+    fn test_gen_between() {
+        let opts = TimeOpts { count: 40, category: "e".to_string(), hour_format: HourFormat::H12, minute_step: 5, max_elapsed: 180, ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(4))) {
+            let text = problem.solved_text();
+            // 从 上午9:40 到 上午11:15 经过了 1小时35分钟
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            assert_eq!((tokens[0], tokens[2]), ("从", "到"));
+            assert_eq!(minutes(tokens[1]) + duration(tokens[5]), minutes(tokens[3]));
+            assert!(tokens[1].starts_with("上午") || tokens[1].starts_with("下午"));
+        }
+    }
+
+    #[test]
+    fn test_gen_clock() {
+        let opts = TimeOpts { count: 40, minute_step: 5, clock: ClockMode::Read, ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(5))) {
+            match problem.segments[0] {
+                Segment::Clock { minutes, hands: true } => assert_eq!(problem.answers, vec![clock_text(minutes)]),
+                _ => panic!("expect a clock with hands"),
+            }
+        }
+        let opts = TimeOpts { clock: ClockMode::Draw, ..opts };
+        let problem = &opts.gen_problems(&mut new_rng(Some(5)))[0];
+        assert!(problem.answers.is_empty());
+        assert!(matches!(problem.segments[1], Segment::Clock { hands: false, .. }));
+    }
+}
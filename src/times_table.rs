@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::seq::index::sample_weighted;
+use crate::TimesTableOpts;
+use crate::err::Error;
+use crate::i18n::t;
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 各个因数的难度，与加法 p1、p3 的 map_freq 一样，越难的出现得越多
+// 一个乘法的次数为两个因数的次数之和减 1，比如 6 × 7 为 5，2 × 10 为 1，表中没有的因数为 2
+fn new_map_freq() -> HashMap<u32, usize> {
+    let mut map_freq: HashMap<u32, usize> = HashMap::new();
+    map_freq.insert(1, 1);
+    map_freq.insert(2, 1);
+    map_freq.insert(3, 2);
+    map_freq.insert(4, 2);
+    map_freq.insert(5, 1);
+    map_freq.insert(6, 3);
+    map_freq.insert(7, 3);
+    map_freq.insert(8, 3);
+    map_freq.insert(9, 2);
+    map_freq.insert(10, 1);
+    map_freq.insert(11, 1);
+    map_freq.insert(12, 2);
+    map_freq
+}
+
+fn fact_freq(map_freq: &HashMap<u32, usize>, l: u32, r: u32) -> usize {
+    let freq = |n: u32| map_freq.get(&n).copied().unwrap_or(2);
+    freq(l) + freq(r) - 1
+}
+
+impl TimesTableOpts {
+    pub fn gen_times_table_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/times-table.docx", Source::new("times-table", self), |rng| Ok(vec![self.gen_section(None, rng)]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Section {
+        let c = &self.category;
+        let wide = c.starts_with('g') || c.starts_with('r');
+        Section {
+            heading,
+            problems: self.gen_problems(rng),
+            columns: self.column_per_page.unwrap_or(if wide { 1 } else { 4 }),
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        }
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Vec<Problem> {
+        let c = &self.category;
+        let count = self.problem_count();
+        if c.starts_with('g') {
+            (0..count).map(|_| self.gen_grid(rng)).collect()
+        } else if c.starts_with('r') || c.starts_with('c') {
+            self.gen_tables(count, rng).into_iter().map(|table| self.gen_fill(table, c.starts_with('r'), rng)).collect()
+        } else {
+            self.gen_drills(count, rng)
+        }
+    }
+
+    // 默认方格 1 个，填一行或一列 4 个，口算 20 个
+    pub fn problem_count(&self) -> usize {
+        let c = &self.category;
+        let default = if c.starts_with('g') { 1 } else if c.starts_with('r') || c.starts_with('c') { 4 } else { 20 };
+        self.count.unwrap_or(default) as usize
+    }
+
+    pub fn default_heading(&self) -> String {
+        let c = &self.category;
+        if c.starts_with('g') {
+            t("heading.times_table")
+        } else if c.starts_with('r') || c.starts_with('c') {
+            t("heading.times_table_fill")
+        } else {
+            t("heading.times_table_drill")
+        }
+    }
+
+    // 第一个因数，默认 1~--max
+    fn tables(&self) -> Vec<u32> {
+        if self.tables.is_empty() {
+            (1..=self.max).collect()
+        } else {
+            self.tables.clone()
+        }
+    }
+
+    // 第二个因数
+    fn factors(&self) -> Vec<u32> {
+        (1..=self.max).collect()
+    }
+
+    // 口算：每轮中每个乘法按 map_freq 出现多次，打乱顺序后取所需的个数，不足时再来一轮
+    fn gen_drills(&self, count: usize, rng: &mut impl Rng) -> Vec<Problem> {
+        let map_freq = new_map_freq();
+        let mut problems = vec![];
+        while problems.len() < count {
+            let mut result_pairs: Vec<Problem> = Vec::new();
+            for table in self.tables() {
+                for factor in self.factors() {
+                    for _ in 0..fact_freq(&map_freq, table, factor) {
+                        // 随机决定前后顺序
+                        let (l, r) = if rng.random_bool(0.5) { (table, factor) } else { (factor, table) };
+                        result_pairs.push(Problem::expr(format!("{:>2} × {:<2}=", l, r), (l * r).to_string())
+                            .with_fact(l as i64, Op::Mul, r as i64));
+                    }
+                }
+            }
+            // 打乱顺序
+            result_pairs.shuffle(rng);
+            result_pairs.truncate(count - problems.len());
+            problems.append(&mut result_pairs);
+        }
+        problems
+    }
+
+    // 乘法表方格，按 --blanks 的比例空出格子，较难的乘法更容易被空出
+    fn gen_grid(&self, rng: &mut impl Rng) -> Problem {
+        let mut rows = self.tables();
+        let mut columns = self.factors();
+        if self.shuffle {
+            rows.shuffle(rng);
+            columns.shuffle(rng);
+        }
+        let map_freq = new_map_freq();
+        let total = rows.len() * columns.len();
+        let amount = (total * self.blanks as usize).div_ceil(100);
+        let weight = |i: usize| fact_freq(&map_freq, rows[i / columns.len()], columns[i % columns.len()]) as f64;
+        let blanks = sample_weighted(rng, total, weight, amount).expect("positive weights").into_vec();
+
+        let mut answers = vec![];
+        let cells = rows.iter().enumerate().map(|(i, row)| columns.iter().enumerate().map(|(j, column)| {
+            let product = (row * column) as i64;
+            if blanks.contains(&(i * columns.len() + j)) {
+                answers.push(product.to_string());
+                None
+            } else {
+                Some(product)
+            }
+        }).collect()).collect();
+        let to_i64 = |v: Vec<u32>| v.into_iter().map(|n| n as i64).collect();
+        Problem { segments: vec![Segment::Grid { rows: to_i64(rows), columns: to_i64(columns), cells }], answers, fact: None }
+    }
+
+    // 从 --tables 中抽取 count 个，全部用完之前不重复
+    fn gen_tables(&self, count: usize, rng: &mut impl Rng) -> Vec<u32> {
+        let mut pool = self.tables();
+        let mut tables = vec![];
+        while tables.len() < count {
+            pool.shuffle(rng);
+            tables.extend(pool.iter().take(count - tables.len()));
+        }
+        tables
+    }
+
+    // 填一行或一列：table 乘以打乱顺序的各个因数，积全部空出
+    fn gen_fill(&self, table: u32, row: bool, rng: &mut impl Rng) -> Problem {
+        let mut factors: Vec<i64> = self.factors().into_iter().map(|n| n as i64).collect();
+        factors.shuffle(rng);
+        let answers = factors.iter().map(|n| (n * table as i64).to_string()).collect();
+        let segment = if row {
+            Segment::Grid { rows: vec![table as i64], cells: vec![vec![None; factors.len()]], columns: factors }
+        } else {
+            Segment::Grid { rows: factors.clone(), columns: vec![table as i64], cells: vec![vec![None]; factors.len()] }
+        };
+        Problem { segments: vec![segment], answers, fact: None }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use crate::TimesTableOpts;
+    use crate::problem::{Op, Segment};
+    use crate::utils::new_rng;
+
+    #[test]
+    fn test_gen_drills() {
+        let opts = TimesTableOpts { count: Some(400), category: "d".to_string(), tables: vec![7], max: 12, ..Default::default() };
+        let mut counts = HashMap::new();
+        for problem in opts.gen_problems(&mut new_rng(Some(13))) {
+            let fact = problem.fact.unwrap();
+            assert_eq!(fact.op, Op::Mul);
+            assert!(fact.left == 7 || fact.right == 7);
+            assert_eq!(problem.answers[0], (fact.left * fact.right).to_string());
+            *counts.entry(fact.left * fact.right).or_insert(0) += 1;
+        }
+        // 7 × 8 比 7 × 10 出现得多
+        assert_eq!(counts.len(), 12);
+        assert!(counts[&56] > counts[&70]);
+    }
+
+    #[test]
+    fn test_gen_grid() {
+        let opts = TimesTableOpts { category: "g".to_string(), max: 12, blanks: 50, shuffle: true, ..Default::default() };
+        let problems = opts.gen_problems(&mut new_rng(Some(14)));
+        assert_eq!(problems.len(), 1);
+        let Segment::Grid { rows, columns, cells } = &problems[0].segments[0] else { panic!("expect a grid") };
+        assert_eq!((rows.len(), columns.len()), (12, 12));
+        let mut answers = problems[0].answers.iter();
+        for (row, cells) in rows.iter().zip(cells) {
+            for (column, cell) in columns.iter().zip(cells) {
+                let product = row * column;
+                match cell {
+                    Some(n) => assert_eq!(*n, product),
+                    None => assert_eq!(answers.next().unwrap(), &product.to_string()),
+                }
+            }
+        }
+        assert_eq!(problems[0].answers.len(), 72);
+    }
+
+    #[test]
+    fn test_gen_fill() {
+        let opts = TimesTableOpts { category: "c".to_string(), tables: vec![3, 7], max: 9, ..Default::default() };
+        let problems = opts.gen_problems(&mut new_rng(Some(15)));
+        assert_eq!(problems.len(), 4);
+        for problem in problems {
+            let Segment::Grid { rows, columns, cells } = &problem.segments[0] else { panic!("expect a grid") };
+            assert_eq!((rows.len(), columns.len()), (9, 1));
+            assert!(cells.iter().all(|row| row == &vec![None]));
+            let answers: Vec<String> = rows.iter().map(|r| (r * columns[0]).to_string()).collect();
+            assert_eq!(problem.answers, answers);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use crate::{AddMinusOpts, UnitFamily, UnitsOpts, utils};
+use crate::add_minus::{gen_operand_pair, operand_opts};
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+impl UnitFamily {
+    // 大单位、小单位的消息键，以及 1 个大单位是多少个小单位
+    fn units(&self) -> (&'static str, &'static str, i64) {
+        match self {
+            UnitFamily::Length => ("units.m", "units.cm", 100),
+            UnitFamily::Mass => ("units.kg", "units.g", 1000),
+            UnitFamily::Volume => ("units.l", "units.ml", 1000),
+            UnitFamily::Time => ("units.h", "units.min", 60),
+        }
+    }
+
+    fn factor(&self) -> i64 {
+        self.units().2
+    }
+
+    // 以小单位计的量写成复名数，比如 345 -> 3米45厘米，为 0 的单位省略
+    pub fn compound_text(&self, amount: i64) -> String {
+        let (big, small, factor) = self.units();
+        let parts: Vec<String> = [(big, amount / factor), (small, amount % factor)].iter()
+            .filter(|(_, n)| *n != 0)
+            .map(|(unit, n)| quantity(*n, unit))
+            .collect();
+        if parts.is_empty() {
+            return quantity(0, small);
+        }
+        parts.join(&t("units.separator"))
+    }
+
+    // 作答处的单位：[空白]米[空白]厘米，答案是大单位和小单位的数
+    fn compound_blanks(&self, amount: i64) -> (Vec<Segment>, Vec<String>) {
+        let (big, small, factor) = self.units();
+        let segments = vec![
+            Segment::Blank(2),
+            Segment::Text(unit_label(big) + &t("units.separator")),
+            Segment::Blank(2),
+            Segment::Text(unit_label(small)),
+        ];
+        (segments, vec![(amount / factor).to_string(), (amount % factor).to_string()])
+    }
+}
+
+// 带单位的量，比如 3米 或 3 m
+fn quantity(n: i64, unit: &str) -> String {
+    tf("units.quantity", &[("n", &n), ("unit", &t(unit))])
+}
+
+// 空白之后的单位，与 quantity 中数和单位之间的写法一致
+fn unit_label(unit: &str) -> String {
+    tf("units.quantity", &[("n", &""), ("unit", &t(unit))])
+}
+
+impl UnitsOpts {
+    pub fn gen_units_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/units.docx", Source::new("units", self), |rng| Ok(vec![self.gen_section(None, rng)?]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Result<Section, Error> {
+        Ok(Section {
+            heading,
+            problems: self.gen_problems(rng)?,
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: false,
+            answer_style: self.layout.answer_style,
+        })
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Result<Vec<Problem>, Error> {
+        (0..self.count).map(|_| {
+            let family = *self.families.choose(rng).unwrap();
+            let c = &self.category;
+            if c.starts_with('c') {
+                self.gen_conversion(family, rng)
+            } else if c.starts_with('+') {
+                self.gen_compound(family, Op::Add, rng)
+            } else if c.starts_with('_') {
+                self.gen_compound(family, Op::Minus, rng)
+            } else {
+                match rng.random_range(0..3) {
+                    0 => self.gen_conversion(family, rng),
+                    1 => self.gen_compound(family, Op::Add, rng),
+                    _ => self.gen_compound(family, Op::Minus, rng),
+                }
+            }
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.units")
+    }
+
+    // 以小单位计的数的范围和结果范围，C* 保证是 step 的倍数，沿用加减法的校验
+    fn operand_args(&self, family: UnitFamily) -> AddMinusOpts {
+        let factor = family.factor();
+        operand_opts(format!("{0}*,{0}*", self.step),
+                     self.number_min_inclusive.saturating_mul(factor)..=self.number_max_inclusive.saturating_mul(factor),
+                     self.result_min_inclusive.saturating_mul(factor)..=self.result_max_inclusive.saturating_mul(factor))
+    }
+
+    // 3米45厘米 + 2米70厘米 = [ ]米[ ]厘米，--carry 时小单位必须进位或退位
+    fn gen_compound(&self, family: UnitFamily, op: Op, rng: &mut impl Rng) -> Result<Problem, Error> {
+        let factor = family.factor();
+        let carried = |(l, r): (i64, i64)| match op {
+            Op::Minus => l % factor < r % factor,
+            _ => l % factor + r % factor >= factor,
+        };
+        // step 为 factor 的倍数时不可能进位
+        let carry = self.carry && self.step % factor != 0;
+        let (l, r) = gen_operand_pair(&self.operand_args(family), op, |p| !carry || carried(p), rng)?;
+        let answer = if op == Op::Minus { l - r } else { l + r };
+        let question = format!("{} {} {} = ", family.compound_text(l), op.symbol(), family.compound_text(r));
+        let (blanks, answers) = family.compound_blanks(answer);
+        Ok(Problem { segments: [vec![Segment::Text(question)], blanks].concat(), answers, fact: None })
+    }
+
+    // 单位换算：3米45厘米 = [ ]厘米，345厘米 = [ ]米[ ]厘米，整数个大单位时 4米 = [ ]厘米 或 400厘米 = [ ]米
+    // -l 大于 -r 时与复名数加减法返回相同的错误
+    fn gen_conversion(&self, family: UnitFamily, rng: &mut impl Rng) -> Result<Problem, Error> {
+        let (big, small, factor) = family.units();
+        let args = self.operand_args(family);
+        let (min, max) = (args.number_min_inclusive, args.number_max_inclusive);
+        if min > max {
+            return Err(Error::Parse(tf("error.number_range", &[("min", &min), ("max", &max)])));
+        }
+        let range = min..=max;
+        // 不足一个大单位的量换算没有意义，范围允许时重新选取
+        let amount = loop {
+            let amount = utils::round_to(rng.random_range(range.clone()), self.step, &range);
+            if amount >= factor || *range.end() < factor {
+                break amount;
+            }
+        };
+        let (question, mut segments, answers) = match (amount % factor == 0, rng.random_bool(0.5)) {
+            (true, true) => (quantity(amount / factor, big), vec![Segment::Blank(2), Segment::Text(unit_label(small))], vec![amount.to_string()]),
+            (true, false) => (quantity(amount, small), vec![Segment::Blank(2), Segment::Text(unit_label(big))], vec![(amount / factor).to_string()]),
+            (false, true) => (family.compound_text(amount), vec![Segment::Blank(2), Segment::Text(unit_label(small))], vec![amount.to_string()]),
+            (false, false) => {
+                let (blanks, answers) = family.compound_blanks(amount);
+                (quantity(amount, small), blanks, answers)
+            }
+        };
+        segments.insert(0, Segment::Text(format!("{} = ", question)));
+        Ok(Problem { segments, answers, fact: None })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{UnitFamily, UnitsOpts};
+    use crate::utils::new_rng;
+
+    // "3米45厘米" -> 345
+    fn centimeters(text: &str) -> i64 {
+        match text.strip_suffix("厘米") {
+            Some(rest) => match rest.split_once('米') {
+                Some((m, cm)) => m.parse::<i64>().unwrap() * 100 + cm.parse::<i64>().unwrap(),
+                None => rest.parse().unwrap(),
+            },
+            None => text.strip_suffix('米').unwrap().parse::<i64>().unwrap() * 100,
+        }
+    }
+
+    #[test]
+    fn test_compound_text() {
+        assert_eq!(UnitFamily::Length.compound_text(345), "3米45厘米");
+        assert_eq!(UnitFamily::Length.compound_text(300), "3米");
+        assert_eq!(UnitFamily::Mass.compound_text(45), "45克");
+        assert_eq!(UnitFamily::Time.compound_text(95), "1小时35分钟");
+        assert_eq!(UnitFamily::Volume.compound_text(0), "0毫升");
+    }
+
+    #[test]
+    fn test_gen_compound() {
+        let opts = UnitsOpts {
+            count: 40,
+            category: "_".to_string(),
+            families: vec![UnitFamily::Length],
+            step: 5,
+            number_max_inclusive: 10,
+            result_max_inclusive: 10,
+            carry: true,
+            ..Default::default()
+        };
+        for problem in opts.gen_problems(&mut new_rng(Some(1))).unwrap() {
+            // 3米45厘米 - 2米70厘米 = 0米75厘米
+            let text = problem.solved_text();
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            let (l, r, ans) = (centimeters(tokens[0]), centimeters(tokens[2]), centimeters(tokens[4]));
+            assert_eq!(l - r, ans);
+            assert!(l % 100 < r % 100, "{}", text);
+            assert!(l % 5 == 0 && r % 5 == 0 && (0..=1000).contains(&l) && ans >= 0);
+            assert_eq!(problem.answers, vec![(ans / 100).to_string(), (ans % 100).to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_gen_conversion() {
+        let opts = UnitsOpts { count: 40, category: "c".to_string(), families: vec![UnitFamily::Length], step: 5, number_max_inclusive: 10, ..Default::default() };
+        for problem in opts.gen_problems(&mut new_rng(Some(2))).unwrap() {
+            // 左右两边是同一个长度
+            let text = problem.solved_text();
+            let (left, right) = text.split_once(" = ").unwrap();
+            assert_eq!(centimeters(left), centimeters(right), "{}", text);
+        }
+
+        let opts = UnitsOpts { number_min_inclusive: 10, number_max_inclusive: 2, ..opts };
+        assert!(opts.gen_problems(&mut new_rng(Some(2))).is_err());
+    }
+}
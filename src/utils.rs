@@ -1,10 +1,13 @@
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::ops::Range;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use docx_rs::{Docx, Paragraph, read_docx, Run, RunFonts};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use crate::err::Error;
+use crate::i18n::{t, tf};
 
 pub fn read_from_docx(filepath: &str) -> Docx {
     let mut file = File::open(filepath).unwrap();
@@ -19,21 +22,23 @@ pub fn write_to_docx(docx: Docx, filepath: &str) {
     let pack_result = docx.build().pack(output_file);
 
     match pack_result {
-        Ok(_) => println!("Generate docs successfully"),
-        Err(e) => eprintln!("Error: {:?}", e),
+        Ok(_) => println!("{}", t("doc.generated_docx")),
+        Err(e) => eprintln!("{}", tf("error.prefix", &[("error", &e)])),
     }
 }
 
 pub fn add_paragraph(docx: Docx, font_size: usize, text: &str) -> Docx {
-    docx.add_paragraph(
-        Paragraph::new().size(font_size)
-            .add_run(Run::new().size(font_size).fonts(RunFonts::new().ascii("Courier New")).add_text(text)))
+    docx.add_paragraph(new_paragraph(font_size, text))
+}
+
+pub fn new_paragraph(font_size: usize, text: &str) -> Paragraph {
+    Paragraph::new().size(font_size)
+        .add_run(Run::new().size(font_size).fonts(RunFonts::new().ascii("Courier New")).add_text(text))
 }
 
 pub fn create_dir_if_necessary(path: &str) {
-    match File::open(PathBuf::from(path)) {
-        Err(_) => { fs::create_dir_all(path).expect("TODO: panic message"); },
-        _ => {}
+    if File::open(PathBuf::from(path)).is_err() {
+        fs::create_dir_all(path).expect("TODO: panic message");
     }
 }
 
@@ -43,7 +48,7 @@ pub fn read(path: PathBuf) -> Result<String, Error> {
     let mut input_file = open(path)?;
     input_file.read_to_string(&mut buffer)?;
     if buffer.is_empty() {
-        return Err("input file missing")?; // ? 配合From 实现自动转换为定制错误Error
+        Err("error.input_file_missing")?; // ? 配合From 实现自动转换为定制错误Error
     }
     Ok(buffer)
 }
@@ -62,7 +67,7 @@ pub fn write(content: &str, filename: &str) -> Result<(), Error> {
 }
 
 // 数字长度， 也可以转换为string再计算， 但性能更差
-pub fn char_len(mut number: u16) -> u16 {
+pub fn char_len(mut number: u64) -> u16 {
     let mut len = 0;
     if number == 0 {
         len = 1;
@@ -74,36 +79,100 @@ pub fn char_len(mut number: u16) -> u16 {
     len
 }
 
-// number转换为最近的 multiple 的倍数
-pub fn round_to(number: u16, multiple: u16, range: &Range<u16>) -> u16 {
-    let mut times = number / multiple;
-    let r = number % multiple;
+// 从个位(第0位)开始数第 position 位上的数字，超出位数时为 0
+pub fn digit_at(number: u64, position: u32) -> u64 {
+    10u64.checked_pow(position).map_or(0, |unit| number / unit % 10)
+}
+
+// 从个位起每三位插入分隔符，比如 1234567 -> 1,234,567
+pub fn group_digits(number: u64, separator: &str) -> String {
+    let digits = number.to_string();
+    let mut text = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push_str(separator);
+        }
+        text.push(c);
+    }
+    text
+}
+
+// number转换为最近的 multiple 的倍数，超出 range 时取相邻的倍数
+pub fn round_to(number: i64, multiple: i64, range: &RangeInclusive<i64>) -> i64 {
+    let mut times = number.div_euclid(multiple);
+    let r = number.rem_euclid(multiple);
     times = if r > multiple / 2 { times + 1 } else { times };
-    let mut ans = times * multiple;
-    if ans >= range.end {
-        ans = (times - 1) * multiple;
+    let multiple_of = |times: i64| times.checked_mul(multiple);
+    let mut ans = multiple_of(times);
+    if ans.is_none_or(|ans| ans > *range.end()) {
+        ans = times.checked_sub(1).and_then(multiple_of);
     }
-    if ans < range.start {
-        ans = (times + 1) * multiple;
+    if ans.is_none_or(|ans| ans < *range.start()) {
+        ans = times.checked_add(1).and_then(multiple_of);
     }
-    ans
+    ans.unwrap_or(number)
+}
+
+// 指定种子时生成可重现的随机数，否则随机选取种子并打印出来，便于之后重现
+pub fn new_rng(seed: Option<u64>) -> StdRng {
+    StdRng::seed_from_u64(seed_or_random(seed))
+}
+
+pub fn seed_or_random(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| {
+        let seed = rand::random();
+        println!("seed: {}", seed);
+        seed
+    })
+}
+
+// 按空白拆分命令行参数，双引号内的空白不拆分
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut quoted = false;
+    let mut pending = false;
+    for c in line.chars() {
+        match c {
+            '"' => { quoted = !quoted; pending = true; },
+            c if c.is_whitespace() && !quoted => {
+                if pending {
+                    args.push(std::mem::take(&mut arg));
+                    pending = false;
+                }
+            },
+            c => { arg.push(c); pending = true; },
+        }
+    }
+    if pending {
+        args.push(arg);
+    }
+    args
+}
+
+// html 中的文本和属性值需要转义的字符
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
 }
 
 // 单元测试
 // 条件编译：只有执行cargo test时才编译下面的模块
 #[cfg(test)]
 mod test {
-    use std::ops::Range;
     use std::path::PathBuf;
-    use crate::utils::{char_len, read, round_to};
+    use crate::utils::{char_len, digit_at, group_digits, read, round_to, split_args};
 
     #[test]
     fn test_round_to() {
-        assert_eq!(round_to(19, 5, &(1u16..100u16)), 20);
-        assert_eq!(round_to(18, 5, &(1u16..100u16)), 20);
-        assert_eq!(round_to(17, 5, &(1u16..100u16)), 15);
-        assert_eq!(round_to(16, 5, &(1u16..100u16)), 15);
-        assert_eq!(round_to(15, 5, &(1u16..100u16)), 15);
+        assert_eq!(round_to(19, 5, &(1..=99)), 20);
+        assert_eq!(round_to(18, 5, &(1..=99)), 20);
+        assert_eq!(round_to(17, 5, &(1..=99)), 15);
+        assert_eq!(round_to(16, 5, &(1..=99)), 15);
+        assert_eq!(round_to(15, 5, &(1..=99)), 15);
+        assert_eq!(round_to(98, 5, &(1..=99)), 95);
+        assert_eq!(round_to(-17, 5, &(-20..=20)), -15);
+        assert_eq!(round_to(-19, 5, &(-19..=20)), -15);
+        assert_eq!(round_to(i64::MAX - 1, 10, &(0..=i64::MAX)), i64::MAX - 7);
     }
 
     #[test]
@@ -113,6 +182,18 @@ mod test {
         assert!(csv_data.is_ok());
     }
 
+    #[test]
+    fn test_digit_at() {
+        assert_eq!(digit_at(4072, 0), 2);
+        assert_eq!(digit_at(4072, 2), 0);
+        assert_eq!(digit_at(4072, 3), 4);
+        assert_eq!(digit_at(4072, 7), 0);
+        assert_eq!(digit_at(u64::MAX, 25), 0);
+        assert_eq!(group_digits(1234567, ","), "1,234,567");
+        assert_eq!(group_digits(372, ","), "372");
+        assert_eq!(group_digits(4372, ""), "4372");
+    }
+
     #[test]
     fn test_char_len() {
         assert_eq!(2, char_len(23));
@@ -123,5 +204,15 @@ mod test {
         assert_eq!(2, char_len(99));
         assert_eq!(3, char_len(100));
         assert_eq!(5, char_len(10000));
+        assert_eq!(7, char_len(1234567));
+        assert_eq!(20, char_len(u64::MAX));
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args("add-minus -n 20  -c +"), vec!["add-minus", "-n", "20", "-c", "+"]);
+        assert_eq!(split_args("--heading \"一、 加法\" add-minus"), vec!["--heading", "一、 加法", "add-minus"]);
+        assert_eq!(split_args("-p \"\""), vec!["-p", ""]);
+        assert!(split_args("  ").is_empty());
     }
 }
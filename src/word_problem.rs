@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize, Serializer};
+use crate::{AddMinusOpts, WordProblemsOpts};
+use crate::add_minus::{gen_operand_pair, operand_opts};
+use crate::err::Error;
+use crate::i18n::{lang, t, tf};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::problem::{Op, Problem, Segment};
+
+// 内置的模板，其他语言使用英文
+const ZH: &str = include_str!("../resources/word_problems/zh.toml");
+const EN: &str = include_str!("../resources/word_problems/en.toml");
+
+// 一种语言的应用题模板，格式见 resources/word_problems/zh.toml
+#[derive(Debug, Clone, Deserialize)]
+pub struct Templates {
+    // 模板文件，内置模板为空
+    #[serde(skip)]
+    source: String,
+    // 数和单位的组合，比如 "{n}{unit}"
+    quantity: String,
+    #[serde(default)]
+    names: Vec<String>,
+    // 单位的各种写法，one 和 other 用于 {a|unit}，其他字段用于 {unit.field}
+    #[serde(default)]
+    units: HashMap<String, HashMap<String, String>>,
+    #[serde(rename = "template")]
+    templates: Vec<Template>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Template {
+    op: Op,
+    #[serde(default)]
+    answer: AnswerKind,
+    unit: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AnswerKind {
+    #[default]
+    Number,
+    Quantity,
+}
+
+impl Templates {
+    // 作为命令行参数的解析函数，目录中按语言选取 <lang>.toml
+    pub fn load(path: &str) -> Result<Templates, Error> {
+        let file = if Path::new(path).is_dir() {
+            Path::new(path).join(format!("{}.toml", lang()))
+        } else {
+            Path::new(path).to_path_buf()
+        };
+        let templates = Templates::parse(&fs::read_to_string(&file)?)?;
+        Ok(Templates { source: path.to_string(), ..templates })
+    }
+
+    // 当前语言的内置模板
+    pub fn builtin() -> Templates {
+        let content = if lang() == "zh" { ZH } else { EN };
+        Templates::parse(content).expect("Invalid built-in word problem templates")
+    }
+
+    // 解析并检查每个模板，模板中的标记都必须能够替换
+    fn parse(content: &str) -> Result<Templates, Error> {
+        let templates: Templates = toml::from_str(content)?;
+        if templates.templates.is_empty() {
+            return Err(Error::Parse(t("error.template_empty")));
+        }
+        for (i, template) in templates.templates.iter().enumerate() {
+            templates.check(template).map_err(|reason| Error::Parse(tf("error.template", &[("n", &(i + 1)), ("reason", &reason)])))?;
+        }
+        Ok(templates)
+    }
+
+    fn check(&self, template: &Template) -> Result<(), String> {
+        if !matches!(template.op, Op::Add | Op::Minus) {
+            return Err(t("error.template_op"));
+        }
+        if template.answer == AnswerKind::Quantity {
+            self.answer_blank(template)?;
+        }
+        let name = self.names.first().map(|s| s.as_str()).unwrap_or("");
+        self.render(&template.text, (2, 1, 1), name).map(|_| ())
+    }
+
+    // 替换模板中的标记，values 为 a, b, ans
+    fn render(&self, text: &str, values: (i64, i64, i64), name: &str) -> Result<String, String> {
+        let value = |tag: &str| match tag {
+            "a" => Ok(values.0),
+            "b" => Ok(values.1),
+            "ans" => Ok(values.2),
+            _ => Err(tf("error.template_tag", &[("tag", &tag)])),
+        };
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                return Err(tf("error.template_tag", &[("tag", &&rest[start + 1..])]));
+            };
+            let tag = &rest[start + 1..start + end];
+            let replaced = if tag == "name" {
+                if self.names.is_empty() {
+                    return Err(t("error.template_names"));
+                }
+                name.to_string()
+            } else if let Some((var, unit)) = tag.split_once('|') {
+                self.quantity(value(var)?, unit)?
+            } else if let Some((unit, field)) = tag.split_once('.') {
+                self.field(unit, field)?.to_string()
+            } else {
+                value(tag)?.to_string()
+            };
+            result.push_str(&replaced);
+            rest = &rest[start + end + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn field(&self, unit: &str, field: &str) -> Result<&str, String> {
+        let forms = self.units.get(unit).ok_or_else(|| tf("error.template_unit", &[("unit", &unit)]))?;
+        forms.get(field).map(|s| s.as_str()).ok_or_else(|| tf("error.template_field", &[("unit", &unit), ("field", &field)]))
+    }
+
+    // 按数选择单数或复数的写法，比如 1 apple, 3 apples, 3个苹果
+    fn quantity(&self, n: i64, unit: &str) -> Result<String, String> {
+        let form = self.field(unit, if n == 1 { "one" } else { "other" })?;
+        Ok(self.quantity.replace("{n}", &n.to_string()).replace("{unit}", form))
+    }
+
+    // 作答处，答案只填数，带单位时单位按 quantity 写在空白前后，比如 [ ]朵花 或 [ ] flowers
+    fn answer_blank(&self, template: &Template) -> Result<Vec<Segment>, String> {
+        let unit = match (template.answer, &template.unit) {
+            (AnswerKind::Number, _) => return Ok(vec![Segment::Blank(4)]),
+            (AnswerKind::Quantity, Some(unit)) => unit,
+            (AnswerKind::Quantity, None) => return Err(t("error.template_answer_unit")),
+        };
+        let quantity = self.quantity.replace("{unit}", self.field(unit, "other")?);
+        let (before, after) = quantity.split_once("{n}").unwrap_or(("", &quantity));
+        let mut segments = vec![Segment::Blank(4), Segment::Text(after.to_string())];
+        if !before.is_empty() {
+            segments.insert(0, Segment::Text(before.to_string()));
+        }
+        Ok(segments)
+    }
+}
+
+// 清单中只记录模板文件
+impl Serialize for Templates {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl WordProblemsOpts {
+    pub fn gen_word_problems_to_docx(&self) -> Result<(), Error> {
+        self.sheet.write(&self.layout, None, "./output/word-problems.docx", Source::new("word-problems", self), |rng| Ok(vec![self.gen_section(None, rng)?]))
+    }
+
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Result<Section, Error> {
+        Ok(Section {
+            heading,
+            problems: self.gen_problems(rng)?,
+            columns: self.column_per_page,
+            font_size: self.output_docx_font_size as usize,
+            spaced: true,
+            answer_style: self.layout.answer_style,
+        })
+    }
+
+    pub fn gen_problems(&self, rng: &mut impl Rng) -> Result<Vec<Problem>, Error> {
+        let templates = self.templates.clone().unwrap_or_else(Templates::builtin);
+        let c = &self.category;
+        let op = if c.starts_with('+') {
+            Some(Op::Add)
+        } else if c.starts_with('_') {
+            Some(Op::Minus)
+        } else {
+            None
+        };
+        // 没有该运算的模板时使用全部模板
+        let mut candidates: Vec<&Template> = templates.templates.iter().filter(|t| op.is_none_or(|op| t.op == op)).collect();
+        if candidates.is_empty() {
+            candidates = templates.templates.iter().collect();
+        }
+        (0..self.count).map(|_| {
+            let template = *candidates.choose(rng).unwrap();
+            self.gen_problem(&templates, template, rng)
+        }).collect()
+    }
+
+    pub fn default_heading(&self) -> String {
+        t("heading.word_problems")
+    }
+
+    // 运算数的范围和模式，沿用加减法的校验
+    fn operand_args(&self) -> AddMinusOpts {
+        operand_opts(self.operand_pattern.clone(), self.number_min_inclusive..=self.number_max_inclusive,
+                     self.result_min_inclusive..=self.result_max_inclusive)
+    }
+
+    fn gen_problem(&self, templates: &Templates, template: &Template, rng: &mut impl Rng) -> Result<Problem, Error> {
+        let (a, b) = gen_operand_pair(&self.operand_args(), template.op, |_| true, rng)?;
+        let ans = if template.op == Op::Minus { a - b } else { a + b };
+        let name = templates.names.choose(rng).map(|s| s.as_str()).unwrap_or("");
+        // 模板在加载时已经检查过
+        let text = templates.render(&template.text, (a, b, ans), name).expect("checked template");
+        let blank = templates.answer_blank(template).expect("checked template");
+        let segments = [vec![Segment::Text(text + " ")], blank].concat();
+        Ok(Problem { segments, answers: vec![ans.to_string()], fact: None }
+            .with_fact(a, template.op, b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use crate::WordProblemsOpts;
+    use crate::problem::Op;
+    use crate::utils::new_rng;
+    use crate::word_problem::{Templates, EN, ZH};
+
+    #[test]
+    fn test_builtin_templates() {
+        assert!(Templates::parse(ZH).is_ok());
+        assert!(Templates::parse(EN).is_ok());
+    }
+
+    #[test]
+    fn test_render() {
+        let templates = Templates::parse(EN).unwrap();
+        let text = "{name} has {a|apple} and {b|apple}, {ans} {apple.other}";
+        assert_eq!(templates.render(text, (1, 3, 4), "Sam").unwrap(), "Sam has 1 apple and 3 apples, 4 apples");
+        assert!(templates.render("{a|pear}", (1, 1, 2), "").is_err());
+        assert!(templates.render("{c}", (1, 1, 2), "").is_err());
+        assert!(templates.render("{apple.measure}", (1, 1, 2), "").is_err());
+        assert!(templates.render("{a", (1, 1, 2), "").is_err());
+
+        let zh = Templates::parse(ZH).unwrap();
+        assert_eq!(zh.render("{a|apple}，{b|book}", (1, 3, 4), "").unwrap(), "1个苹果，3本书");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let template = |text: &str, extra: &str| format!("quantity = \"{{n}} {{unit}}\"\n[units.apple]\none = \"apple\"\nother = \"apples\"\n[[template]]\nop = \"-\"\n{}\ntext = \"{}\"\n", extra, text);
+        assert!(Templates::parse(&template("{a|apple} - {b}", "answer = \"quantity\"\nunit = \"apple\"")).is_ok());
+        assert!(Templates::parse(&template("{a|pear}", "")).is_err());
+        assert!(Templates::parse(&template("{name}", "")).is_err());
+        assert!(Templates::parse(&template("{a}", "answer = \"quantity\"")).is_err());
+        assert!(Templates::parse(&template("{a}", "answer = \"quantity\"\nunit = \"pear\"")).is_err());
+    }
+
+    #[test]
+    fn test_gen_problems() {
+        let opts = WordProblemsOpts {
+            count: 40,
+            category: "_".to_string(),
+            operand_pattern: "*,*".to_string(),
+            number_min_inclusive: 1,
+            number_max_inclusive: 20,
+            result_max_inclusive: 40,
+            ..Default::default()
+        };
+        let mut counts = HashMap::new();
+        for problem in opts.gen_problems(&mut new_rng(Some(6))).unwrap() {
+            let fact = problem.fact.unwrap();
+            assert_eq!(fact.op, Op::Minus);
+            let ans = fact.left - fact.right;
+            assert!((1..=20).contains(&fact.left) && (0..=40).contains(&ans));
+            // 答案只有数，带单位时单位写在空白之后
+            assert_eq!(problem.answers, vec![ans.to_string()]);
+            assert!(problem.is_correct(&[ans.to_string()]));
+            assert!(problem.solved_text().contains(&format!("{}", fact.left)));
+            *counts.entry(problem.segments.len()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2);
+    }
+}
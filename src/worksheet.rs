@@ -0,0 +1,220 @@
+use clap::Parser;
+use rand::Rng;
+use crate::err::Error;
+use crate::i18n::{t, tf};
+use crate::{add_minus, Generator, LayoutOpts, NumberStyle, SectionCli, SheetOpts, WorksheetOpts};
+use crate::layout::Section;
+use crate::manifest::Source;
+use crate::utils::split_args;
+
+impl Generator {
+    // 加减法类的参数可能互相矛盾，生成不出算式时返回错误
+    pub fn gen_section(&self, heading: Option<String>, rng: &mut impl Rng) -> Result<Section, Error> {
+        match self {
+            Generator::AddMinus(opts) => add_minus::gen_section(opts, heading, rng),
+            Generator::MissingNumber(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::Fractions(opts) => opts.gen_section(heading, rng),
+            Generator::Time(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::Units(opts) => opts.gen_section(heading, rng),
+            Generator::WordProblems(opts) => opts.gen_section(heading, rng),
+            Generator::NumberBonds(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::PlaceValue(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::Rounding(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::TimesTable(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::LongMultiplication(opts) => Ok(opts.gen_section(heading, rng)),
+            Generator::LongDivision(opts) => Ok(opts.gen_section(heading, rng)),
+        }
+    }
+
+    pub fn layout(&self) -> &LayoutOpts {
+        match self {
+            Generator::AddMinus(opts) => &opts.layout,
+            Generator::MissingNumber(opts) => &opts.layout,
+            Generator::Fractions(opts) => &opts.layout,
+            Generator::Time(opts) => &opts.layout,
+            Generator::Units(opts) => &opts.layout,
+            Generator::WordProblems(opts) => &opts.layout,
+            Generator::NumberBonds(opts) => &opts.layout,
+            Generator::PlaceValue(opts) => &opts.layout,
+            Generator::Rounding(opts) => &opts.layout,
+            Generator::TimesTable(opts) => &opts.layout,
+            Generator::LongMultiplication(opts) => &opts.layout,
+            Generator::LongDivision(opts) => &opts.layout,
+        }
+    }
+
+    pub fn sheet(&self) -> &SheetOpts {
+        match self {
+            Generator::AddMinus(opts) => &opts.sheet,
+            Generator::MissingNumber(opts) => &opts.sheet,
+            Generator::Fractions(opts) => &opts.sheet,
+            Generator::Time(opts) => &opts.sheet,
+            Generator::Units(opts) => &opts.sheet,
+            Generator::WordProblems(opts) => &opts.sheet,
+            Generator::NumberBonds(opts) => &opts.sheet,
+            Generator::PlaceValue(opts) => &opts.sheet,
+            Generator::Rounding(opts) => &opts.sheet,
+            Generator::TimesTable(opts) => &opts.sheet,
+            Generator::LongMultiplication(opts) => &opts.sheet,
+            Generator::LongDivision(opts) => &opts.sheet,
+        }
+    }
+
+    // 题目数量只取决于参数，与随机数无关
+    pub fn problem_count(&self) -> usize {
+        match self {
+            Generator::AddMinus(opts) => opts.count as usize,
+            Generator::MissingNumber(opts) => opts.count as usize,
+            Generator::Fractions(opts) => opts.count as usize,
+            Generator::Time(opts) => opts.count as usize,
+            Generator::Units(opts) => opts.count as usize,
+            Generator::WordProblems(opts) => opts.count as usize,
+            Generator::NumberBonds(opts) => opts.count as usize,
+            Generator::PlaceValue(opts) => opts.count as usize,
+            Generator::Rounding(opts) => opts.count as usize,
+            Generator::TimesTable(opts) => opts.problem_count(),
+            Generator::LongMultiplication(opts) => opts.count as usize,
+            Generator::LongDivision(opts) => opts.count as usize,
+        }
+    }
+
+    fn default_heading(&self) -> String {
+        match self {
+            Generator::AddMinus(opts) => add_minus::default_heading(opts),
+            Generator::MissingNumber(_) => t("heading.missing_number"),
+            Generator::Fractions(opts) => opts.default_heading(),
+            Generator::Time(opts) => opts.default_heading(),
+            Generator::Units(opts) => opts.default_heading(),
+            Generator::WordProblems(opts) => opts.default_heading(),
+            Generator::NumberBonds(opts) => opts.default_heading(),
+            Generator::PlaceValue(opts) => opts.default_heading(),
+            Generator::Rounding(opts) => opts.default_heading(),
+            Generator::TimesTable(opts) => opts.default_heading(),
+            Generator::LongMultiplication(opts) => opts.default_heading(),
+            Generator::LongDivision(opts) => opts.default_heading(),
+        }
+    }
+
+    pub fn print_adaptive_weights(&self) {
+        if let Generator::AddMinus(opts) = self {
+            add_minus::print_adaptive_weights(opts);
+        }
+    }
+
+    // 整份试卷统一题号样式
+    fn set_numbering(&mut self, numbering: NumberStyle) {
+        match self {
+            Generator::AddMinus(opts) => opts.layout.numbering = numbering,
+            Generator::MissingNumber(opts) => opts.layout.numbering = numbering,
+            Generator::Fractions(opts) => opts.layout.numbering = numbering,
+            Generator::Time(opts) => opts.layout.numbering = numbering,
+            Generator::Units(opts) => opts.layout.numbering = numbering,
+            Generator::WordProblems(opts) => opts.layout.numbering = numbering,
+            Generator::NumberBonds(opts) => opts.layout.numbering = numbering,
+            Generator::PlaceValue(opts) => opts.layout.numbering = numbering,
+            Generator::Rounding(opts) => opts.layout.numbering = numbering,
+            Generator::TimesTable(opts) => opts.layout.numbering = numbering,
+            Generator::LongMultiplication(opts) => opts.layout.numbering = numbering,
+            Generator::LongDivision(opts) => opts.layout.numbering = numbering,
+        }
+    }
+}
+
+impl WorksheetOpts {
+    pub fn gen_worksheet_to_docx(&self) -> Result<(), Error> {
+        let sections = self.sections.iter()
+            .map(|line| Ok(SectionCli::try_parse_from(split_args(line))?))
+            .collect::<Result<_, Error>>()?;
+        self.write_sections(sections, true)
+    }
+
+    // default_headings 为 true 时，未指定标题的小节使用 "一、加法" 这样的默认标题
+    pub fn write_sections(&self, mut sections: Vec<SectionCli>, default_headings: bool) -> Result<(), Error> {
+        for (i, section) in sections.iter_mut().enumerate() {
+            section.generator.print_adaptive_weights();
+            section.generator.set_numbering(self.layout.numbering);
+            if section.heading.is_none() && default_headings {
+                section.heading = Some(tf("doc.section_heading", &[("cn", &chinese_ordinal(i + 1)), ("n", &(i + 1)), ("heading", &section.generator.default_heading())]));
+            }
+        }
+        set_label_max(&mut sections);
+        // 清单中的小节与 batch 描述文件的格式相同
+        let mut source = Source::new("worksheet", self);
+        source.options["section"] = serde_json::to_value(&sections).unwrap_or_default();
+        self.sheet.write(&self.layout, self.title.as_deref(), &self.output, source, |rng| {
+            sections.iter().map(|s| s.generator.gen_section(s.heading.clone(), rng)).collect()
+        })
+    }
+}
+
+// 题号在各小节之间连续，缺数的题目按整份试卷最大的题号预留题号的宽度
+fn set_label_max(sections: &mut [SectionCli]) {
+    let total = sections.iter().map(|s| s.generator.problem_count()).sum();
+    for section in sections.iter_mut() {
+        if let Generator::MissingNumber(opts) = &mut section.generator {
+            opts.label_max = Some(total);
+        }
+    }
+}
+
+// 小节序号：一、二 ... 九十九，更大的直接用数字
+fn chinese_ordinal(n: usize) -> String {
+    const DIGITS: [&str; 10] = ["", "一", "二", "三", "四", "五", "六", "七", "八", "九"];
+    match n {
+        1..=9 => DIGITS[n].to_string(),
+        10..=19 => format!("十{}", DIGITS[n % 10]),
+        20..=99 => format!("{}十{}", DIGITS[n / 10], DIGITS[n % 10]),
+        _ => n.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use crate::{Generator, SectionCli};
+    use crate::utils::{new_rng, split_args};
+    use crate::worksheet::{chinese_ordinal, set_label_max};
+
+    #[test]
+    fn test_chinese_ordinal() {
+        assert_eq!(chinese_ordinal(1), "一");
+        assert_eq!(chinese_ordinal(10), "十");
+        assert_eq!(chinese_ordinal(12), "十二");
+        assert_eq!(chinese_ordinal(40), "四十");
+        assert_eq!(chinese_ordinal(99), "九十九");
+        assert_eq!(chinese_ordinal(100), "100");
+    }
+
+    #[test]
+    fn test_parse_section() {
+        let section = SectionCli::try_parse_from(split_args("--heading 进位加法 add-minus -n 20 -c + -p 10*,10*")).unwrap();
+        assert_eq!(section.heading.as_deref(), Some("进位加法"));
+        match &section.generator {
+            Generator::AddMinus(opts) => {
+                assert_eq!(opts.count, 20);
+                assert_eq!(opts.operand_pattern, "10*,10*");
+            },
+            _ => panic!("expect add-minus"),
+        }
+
+        let section = SectionCli::try_parse_from(split_args("missing-number -n 5 -s -2")).unwrap();
+        assert!(section.heading.is_none());
+        assert!(matches!(section.generator, Generator::MissingNumber(_)));
+        assert_eq!(section.generator.gen_section(None, &mut new_rng(Some(1))).unwrap().problems.len(), 5);
+
+        assert!(SectionCli::try_parse_from(split_args("add-minus -n 20")).is_err());
+    }
+
+    #[test]
+    fn test_set_label_max() {
+        let mut sections: Vec<SectionCli> = ["add-minus -n 95 -c +", "missing-number -n 5", "times-table -c r"].iter()
+            .map(|line| SectionCli::try_parse_from(split_args(line)).unwrap())
+            .collect();
+        assert_eq!(sections[2].generator.problem_count(), 4);
+        set_label_max(&mut sections);
+        match &sections[1].generator {
+            Generator::MissingNumber(opts) => assert_eq!(opts.label_max, Some(104)),
+            _ => panic!("expect missing-number"),
+        }
+    }
+}
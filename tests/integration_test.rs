@@ -1,7 +1,6 @@
 // 集成测试, 对于二进制类型crate， 集成需要创建 lib.rs 并导出对应的方法
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
 
     #[test]
     fn test_csv_challenge() {